@@ -13,6 +13,8 @@ fn main() -> io::Result<()> {
     codegen(Dictionary::fix42(), "fix42.rs")?;
     // FIX 4.4 is always enabled.
     codegen(Dictionary::fix44(), "fix44.rs")?;
+    #[cfg(feature = "fixt11")]
+    codegen(Dictionary::fixt11(), "fixt11.rs")?;
     Ok(())
 }
 
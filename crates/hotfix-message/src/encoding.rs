@@ -9,4 +9,6 @@ pub use field_access::{FieldType, FieldValueError};
 
 #[cfg(feature = "fix42")]
 pub use definitions::fix42;
+#[cfg(feature = "fixt11")]
+pub use definitions::fixt11;
 pub use definitions::{fix44, HardCodedFixFieldDefinition};
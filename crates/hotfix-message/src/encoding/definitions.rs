@@ -40,7 +40,7 @@ impl dict::IsFieldDefinition for HardCodedFixFieldDefinition {
 #[cfg(feature = "fix42")]
 #[allow(dead_code, unused, warnings, enum_variant_names)]
 #[rustfmt::skip]
-/// Field and message definitions for FIX.4.4.
+/// Field and message definitions for FIX.4.2.
 pub mod fix42 {
     include!(concat!(env!("OUT_DIR"), "/fix42.rs"));
 }
@@ -51,3 +51,11 @@ pub mod fix42 {
 pub mod fix44 {
     include!(concat!(env!("OUT_DIR"), "/fix44.rs"));
 }
+
+#[cfg(feature = "fixt11")]
+#[allow(dead_code, unused, warnings, enum_variant_names)]
+#[rustfmt::skip]
+/// Field and message definitions for FIXT.1.1, the session layer of FIX 5.0 and later.
+pub mod fixt11 {
+    include!(concat!(env!("OUT_DIR"), "/fixt11.rs"));
+}
@@ -4,12 +4,18 @@ mod field_map;
 pub mod message;
 mod parser;
 pub(crate) mod parts;
+pub mod validation;
 
 pub use encoding::field_access::FieldType;
 pub use encoding::field_types;
+#[cfg(feature = "fix42")]
+pub use encoding::fix42;
 pub use encoding::fix44;
+#[cfg(feature = "fixt11")]
+pub use encoding::fixt11;
 use encoding::Buffer;
 pub use encoding::HardCodedFixFieldDefinition;
 pub use hotfix_derive::FieldType;
 pub use hotfix_dictionary::{self as dict, TagU32};
+pub use parser::ParseError;
 pub use parts::{Part, RepeatingGroup};
@@ -2,7 +2,7 @@ use std::io::Write;
 
 use crate::encoder::Encode;
 use crate::field_map::{Field, FieldMap};
-use crate::parser::{MessageParser, SOH};
+use crate::parser::{MessageParser, ParseError, SOH};
 use crate::parts::{Body, Header, Part, RepeatingGroup, Trailer};
 use crate::FieldType;
 use crate::{fix44, HardCodedFixFieldDefinition};
@@ -27,12 +27,25 @@ impl Message {
         msg
     }
 
-    pub fn from_bytes(config: &Config, dict: &Dictionary, data: &[u8]) -> Self {
+    pub fn from_bytes(config: &Config, dict: &Dictionary, data: &[u8]) -> Result<Self, ParseError> {
         let mut builder = MessageParser::new(dict, config, data);
 
         builder.build()
     }
 
+    /// Parses a message of a FIXT session, see [MessageParser::with_dictionaries].
+    pub fn from_bytes_with_dictionaries(
+        config: &Config,
+        transport_dict: &Dictionary,
+        application_dict: &Dictionary,
+        data: &[u8],
+    ) -> Result<Self, ParseError> {
+        let mut builder =
+            MessageParser::with_dictionaries(transport_dict, application_dict, config, data);
+
+        builder.build()
+    }
+
     pub fn encode(&mut self, config: &Config) -> Vec<u8> {
         let mut buffer = Vec::new();
 
@@ -7,8 +7,20 @@ use std::collections::{HashMap, HashSet};
 
 pub const SOH: u8 = 0x1;
 
+/// Why a message couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The message ended before the trailer.
+    #[error("the message ends within the {0}")]
+    UnexpectedEnd(&'static str),
+    /// A tag isn't a positive number.
+    #[error("invalid tag at byte {0}")]
+    InvalidTag(usize),
+}
+
 pub struct MessageParser<'a> {
-    dict: &'a Dictionary,
+    transport_dict: &'a Dictionary,
+    application_dict: &'a Dictionary,
     header_tags: HashSet<TagU32>,
     trailer_tags: HashSet<TagU32>,
     group_tags: HashMap<TagU32, HashSet<TagU32>>,
@@ -19,85 +31,103 @@ pub struct MessageParser<'a> {
 
 impl<'a> MessageParser<'a> {
     pub fn new(dict: &'a Dictionary, config: &'a Config, data: &'a [u8]) -> Self {
+        Self::with_dictionaries(dict, dict, config, data)
+    }
+
+    /// Creates a parser for FIXT sessions, where the header and trailer are defined by the
+    /// transport dictionary and the body by a separate application dictionary.
+    pub fn with_dictionaries(
+        transport_dict: &'a Dictionary,
+        application_dict: &'a Dictionary,
+        config: &'a Config,
+        data: &'a [u8],
+    ) -> Self {
+        let mut group_tags = Self::get_group_tags(transport_dict);
+        if !std::ptr::eq(transport_dict, application_dict) {
+            for (tag, tags) in Self::get_group_tags(application_dict) {
+                group_tags.entry(tag).or_default().extend(tags);
+            }
+        }
+
         Self {
-            dict,
+            transport_dict,
+            application_dict,
             position: 0,
-            header_tags: Self::get_tags_for_component(dict, "StandardHeader"),
-            trailer_tags: Self::get_tags_for_component(dict, "StandardTrailer"),
-            group_tags: Self::get_group_tags(dict),
+            header_tags: Self::get_tags_for_component(transport_dict, "StandardHeader"),
+            trailer_tags: Self::get_tags_for_component(transport_dict, "StandardTrailer"),
+            group_tags,
             raw_data: data,
             config,
         }
     }
 
-    pub(crate) fn build(&mut self) -> Message {
-        let (header, next) = self.build_header();
-        let (body, next) = self.build_body(next);
-        let trailer = self.build_trailer(next);
+    pub(crate) fn build(&mut self) -> Result<Message, ParseError> {
+        let (header, next) = self.build_header()?;
+        let (body, next) = self.build_body(next)?;
+        let trailer = self.build_trailer(next)?;
 
-        Message {
+        Ok(Message {
             header,
             body,
             trailer,
-        }
+        })
     }
 
-    fn build_header(&mut self) -> (Header, Field) {
+    fn build_header(&mut self) -> Result<(Header, Field), ParseError> {
         // first three fields need to be BeginString (8), BodyLength (9), and MsgType(35)
         // https://www.onixs.biz/fix-dictionary/4.4/compblock_standardheader.html
         let mut header = Header::default();
 
         loop {
-            let field = self
-                .next_field()
-                .expect("the message to not end within the header");
+            let field = self.expect_field("header")?;
 
             if self.header_tags.contains(&field.tag) {
                 header.fields.insert(field);
             } else {
-                return (header, field);
+                return Ok((header, field));
             }
         }
     }
 
-    fn build_body(&mut self, next_field: Field) -> (Body, Field) {
+    fn build_body(&mut self, next_field: Field) -> Result<(Body, Field), ParseError> {
         let mut body = Body::default();
         let mut field = next_field;
 
         while !self.trailer_tags.contains(&field.tag) {
-            let tag = field.tag.get();
+            let tag = field.tag;
             body.store_field(field);
 
-            // check if it's the start of a group and parse the group as needed
-            let field_def = self.dict.field_by_tag(tag).unwrap();
-            if field_def.is_num_in_group() {
-                let (groups, next) = self.parse_groups(field_def.tag());
+            // check if it's the start of a group and parse the group as needed,
+            // tags missing from the dictionary are kept as plain fields for validation to catch
+            if self.is_num_in_group(tag.get()) {
+                let (groups, next) = self.parse_groups(tag)?;
                 body.set_groups(groups);
                 field = next;
             } else {
-                field = self
-                    .next_field()
-                    .expect("message to not end within the body");
+                field = self.expect_field("body")?;
             }
         }
 
-        (body, field)
+        Ok((body, field))
     }
 
-    fn build_trailer(&mut self, next_field: Field) -> Trailer {
+    fn build_trailer(&mut self, next_field: Field) -> Result<Trailer, ParseError> {
         // https://www.onixs.biz/fix-dictionary/4.4/compblock_standardtrailer.html
         let mut trailer = Trailer::default();
         let mut field = Some(next_field);
         while let Some(f) = field {
             trailer.store_field(f);
-            field = self.next_field();
+            field = self.next_field()?;
         }
 
-        trailer
+        Ok(trailer)
     }
 
-    fn parse_groups(&mut self, start_tag: TagU32) -> (Vec<RepeatingGroup>, Field) {
-        let first_field = self.next_field().unwrap();
+    fn parse_groups(
+        &mut self,
+        start_tag: TagU32,
+    ) -> Result<(Vec<RepeatingGroup>, Field), ParseError> {
+        let first_field = self.expect_field("group")?;
         let delimiter = first_field.tag;
         let mut groups = vec![];
 
@@ -107,7 +137,7 @@ impl<'a> MessageParser<'a> {
 
             // we store the first field, which is the delimiter
             group.store_field(field);
-            field = self.next_field().unwrap();
+            field = self.expect_field("group")?;
 
             loop {
                 if self
@@ -123,9 +153,8 @@ impl<'a> MessageParser<'a> {
                     } else {
                         let tag = field.tag;
                         group.store_field(field);
-                        let field_def = self.dict.field_by_tag(tag.get()).unwrap();
-                        if field_def.is_num_in_group() {
-                            let (groups, next) = self.parse_groups(tag);
+                        if self.is_num_in_group(tag.get()) {
+                            let (groups, next) = self.parse_groups(tag)?;
                             group.set_groups(groups);
                             field = next;
                             continue;
@@ -134,28 +163,46 @@ impl<'a> MessageParser<'a> {
                 } else {
                     // otherwise we have finished parsing the groups
                     groups.push(group);
-                    return (groups, field);
+                    return Ok((groups, field));
                 }
-                field = self.next_field().unwrap();
+                field = self.expect_field("group")?;
             }
 
             groups.push(group)
         }
     }
 
-    fn next_field(&mut self) -> Option<Field> {
+    fn is_num_in_group(&self, tag: u32) -> bool {
+        self.application_dict
+            .field_by_tag(tag)
+            .or_else(|| self.transport_dict.field_by_tag(tag))
+            .is_some_and(|field_def| field_def.is_num_in_group())
+    }
+
+    fn next_field(&mut self) -> Result<Option<Field>, ParseError> {
         let mut iter = self.raw_data[self.position..].iter();
-        let equal_sign_position = self.position + iter.position(|c| *c == b'=')?;
-        let bytes_until_separator = iter.position(|c| *c == self.config.separator)?;
+        let Some(equal_sign_offset) = iter.position(|c| *c == b'=') else {
+            return Ok(None);
+        };
+        let Some(bytes_until_separator) = iter.position(|c| *c == self.config.separator) else {
+            return Ok(None);
+        };
+        let equal_sign_position = self.position + equal_sign_offset;
         let separator_position = equal_sign_position + bytes_until_separator + 1;
 
-        let tag = tag_from_bytes(&self.raw_data[self.position..equal_sign_position]).unwrap();
+        let tag = tag_from_bytes(&self.raw_data[self.position..equal_sign_position])
+            .ok_or(ParseError::InvalidTag(self.position))?;
         let data = self.raw_data[equal_sign_position + 1..separator_position].to_vec();
         let field = Field::new(tag, data);
 
         self.position = separator_position + 1;
 
-        Some(field)
+        Ok(Some(field))
+    }
+
+    /// Reads the next field of the given part of the message, which mustn't end yet.
+    fn expect_field(&mut self, part: &'static str) -> Result<Field, ParseError> {
+        self.next_field()?.ok_or(ParseError::UnexpectedEnd(part))
     }
 
     fn get_tags_for_component(dict: &Dictionary, component_name: &str) -> HashSet<TagU32> {
@@ -209,7 +256,10 @@ impl<'a> MessageParser<'a> {
 fn tag_from_bytes(bytes: &[u8]) -> Option<TagU32> {
     let mut tag = 0u32;
     for byte in bytes.iter().copied() {
-        tag = tag * 10 + (byte as u32 - b'0' as u32);
+        if !byte.is_ascii_digit() {
+            return None;
+        }
+        tag = tag.checked_mul(10)?.checked_add((byte - b'0') as u32)?;
     }
 
     TagU32::new(tag)
@@ -219,6 +269,7 @@ fn tag_from_bytes(bytes: &[u8]) -> Option<TagU32> {
 mod tests {
     use crate::field_types::Currency;
     use crate::message::{Config, Message};
+    use crate::parser::ParseError;
     use crate::{fix44, Part};
     use hotfix_dictionary::{Dictionary, IsFieldDefinition};
 
@@ -228,7 +279,7 @@ mod tests {
         let raw = b"8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=091|";
         let dict = Dictionary::fix44();
 
-        let message = Message::from_bytes(&config, &dict, raw);
+        let message = Message::from_bytes(&config, &dict, raw).unwrap();
 
         let begin: &str = message.header().get(fix44::BEGIN_STRING).unwrap();
         assert_eq!(begin, "FIX.4.4");
@@ -249,13 +300,31 @@ mod tests {
         assert_eq!(checksum, "091");
     }
 
+    #[test]
+    fn parse_message_with_undefined_tag() {
+        let config = Config { separator: b'|' };
+        let raw = b"8=FIX.4.4|9=48|35=D|49=AFUNDMGR|56=ABROKER|15=USD|9999=X|59=0|10=091|";
+        let dict = Dictionary::fix44();
+
+        let message = Message::from_bytes(&config, &dict, raw).unwrap();
+
+        let undefined_tag = hotfix_dictionary::TagU32::new(9999).unwrap();
+        assert_eq!(
+            message.get_field_map().get_raw(undefined_tag),
+            Some(b"X".as_slice())
+        );
+
+        let time_in_force: &str = message.get(fix44::TIME_IN_FORCE).unwrap();
+        assert_eq!(time_in_force, "0");
+    }
+
     #[test]
     fn repeating_group_entries() {
         let config = Config { separator: b'|' };
         let raw = b"8=FIX.4.4|9=219|35=8|49=SENDER|56=TARGET|34=123|52=20231103-12:00:00|11=12345|17=ABC123|150=2|39=1|55=XYZ|54=1|38=200|44=10|32=100|31=10|14=100|6=10|151=100|136=2|137=100|138=EUR|139=7|137=160|138=GBP|139=7|10=128|";
         let dict = Dictionary::fix44();
 
-        let message = Message::from_bytes(&config, &dict, raw);
+        let message = Message::from_bytes(&config, &dict, raw).unwrap();
         let begin: &str = message.header().get(fix44::BEGIN_STRING).unwrap();
         assert_eq!(begin, "FIX.4.4");
 
@@ -271,13 +340,31 @@ mod tests {
         assert_eq!(checksum, "128");
     }
 
+    #[test]
+    fn malformed_messages_are_errors() {
+        let config = Config { separator: b'|' };
+        let dict = Dictionary::fix44();
+
+        let result = Message::from_bytes(&config, &dict, b"garbled");
+        assert_eq!(result.err(), Some(ParseError::UnexpectedEnd("header")));
+
+        let result = Message::from_bytes(&config, &dict, b"8=FIX.4.4|9=5|35=D|11=A|");
+        assert_eq!(result.err(), Some(ParseError::UnexpectedEnd("body")));
+
+        let result = Message::from_bytes(&config, &dict, b"8=FIX.4.4|x9=5|35=0|10=000|");
+        assert_eq!(result.err(), Some(ParseError::InvalidTag(10)));
+
+        let result = Message::from_bytes(&config, &dict, b"8=FIX.4.4|99999999999=5|10=000|");
+        assert_eq!(result.err(), Some(ParseError::InvalidTag(10)));
+    }
+
     #[test]
     fn nested_repeating_group_entries() {
         let config = Config { separator: b'|' };
         let raw = b"8=FIX.4.4|9=000|35=8|34=2|49=Broker|52=20231103-09:30:00|56=Client|11=Order12345|17=Exec12345|150=0|39=0|55=APPL|54=1|38=100|32=50|31=150.00|151=50|14=50|6=150.00|453=2|448=PARTYA|447=D|452=1|802=2|523=SUBPARTYA1|803=1|523=SUBPARTYA2|803=2|448=PARTYB|447=D|452=2|10=111|";
         let dict = Dictionary::fix44();
 
-        let message = Message::from_bytes(&config, &dict, raw);
+        let message = Message::from_bytes(&config, &dict, raw).unwrap();
         let party_a = message.get_group(fix44::NO_PARTY_I_DS, 0).unwrap();
         let party_a_0 = party_a
             .get_group(fix44::NO_PARTY_SUB_I_DS.tag(), 0)
@@ -295,4 +382,29 @@ mod tests {
         let checksum: &str = message.trailer().get(fix44::CHECK_SUM).unwrap();
         assert_eq!(checksum, "111");
     }
+
+    #[test]
+    fn parse_fixt_message_with_application_dictionary() {
+        let config = Config { separator: b'|' };
+        let raw = b"8=FIXT.1.1|9=000|35=8|34=2|49=Broker|52=20231103-09:30:00|56=Client|1128=9|11=Order12345|17=Exec12345|150=0|39=0|55=APPL|54=1|453=2|448=PARTYA|447=D|452=1|448=PARTYB|447=D|452=2|10=111|";
+        let transport_dict = Dictionary::fixt11();
+        let application_dict = Dictionary::fix50sp2();
+
+        let message =
+            Message::from_bytes_with_dictionaries(&config, &transport_dict, &application_dict, raw)
+                .unwrap();
+
+        let appl_ver_id = hotfix_dictionary::TagU32::new(1128).unwrap();
+        assert_eq!(
+            message.header().get_field_map().get_raw(appl_ver_id),
+            Some(b"9".as_slice())
+        );
+
+        let party_b = message.get_group(fix44::NO_PARTY_I_DS, 1).unwrap();
+        let party_b_id: &str = party_b.get(fix44::PARTY_ID).unwrap();
+        assert_eq!(party_b_id, "PARTYB");
+
+        let checksum: &str = message.trailer().get(fix44::CHECK_SUM).unwrap();
+        assert_eq!(checksum, "111");
+    }
 }
@@ -0,0 +1,513 @@
+//! Checks messages against a [Dictionary].
+use std::collections::HashSet;
+
+use hotfix_dictionary::{Dictionary, FixDatatype, LayoutItem, LayoutItemKind, TagU32};
+use serde::Deserialize;
+
+use crate::field_map::FieldMap;
+use crate::field_types::{Date, MonthYear, Time, Timestamp};
+use crate::message::Message;
+use crate::parts::RepeatingGroup;
+use crate::{fix44, FieldType, Part};
+
+/// Tags from this number onwards are user-defined.
+const FIRST_USER_DEFINED_TAG: u32 = 5000;
+
+/// How strictly messages are checked, named after the equivalent QuickFIX settings.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ValidationSettings {
+    /// Rejects header fields in the body and body fields in the trailer, see ValidateFieldsOutOfOrder.
+    pub validate_fields_out_of_order: bool,
+    /// Rejects user-defined fields (tags 5000 and above) that aren't defined for the message,
+    /// see ValidateUserDefinedFields.
+    pub validate_user_defined_fields: bool,
+    /// Accepts other fields that aren't defined for the message, see AllowUnknownMsgFields.
+    pub allow_unknown_msg_fields: bool,
+}
+
+impl Default for ValidationSettings {
+    fn default() -> Self {
+        Self {
+            validate_fields_out_of_order: true,
+            validate_user_defined_fields: true,
+            allow_unknown_msg_fields: false,
+        }
+    }
+}
+
+/// Why a message doesn't match its dictionary.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("invalid MsgType(35)={0}")]
+    InvalidMsgType(String),
+    #[error("required tag missing: {0}")]
+    RequiredTagMissing(TagU32),
+    #[error("invalid tag number: {0}")]
+    InvalidTagNumber(TagU32),
+    #[error("tag not defined for this message type: {0}")]
+    TagNotDefinedForMessageType(TagU32),
+    #[error("tag specified without a value: {0}")]
+    TagSpecifiedWithoutValue(TagU32),
+    #[error("value is incorrect (out of range) for this tag: {0}")]
+    ValueIsIncorrect(TagU32),
+    #[error("incorrect data format for value: {0}")]
+    IncorrectDataFormat(TagU32),
+    #[error("tag specified out of required order: {0}")]
+    TagSpecifiedOutOfOrder(TagU32),
+    #[error("incorrect NumInGroup count for repeating group: {0}")]
+    IncorrectNumInGroupCount(TagU32),
+}
+
+impl ValidationError {
+    /// The SessionRejectReason(373) to reject the message with.
+    pub fn session_reject_reason(&self) -> fix44::SessionRejectReason {
+        match self {
+            Self::InvalidMsgType(_) => fix44::SessionRejectReason::InvalidMsgtype,
+            Self::RequiredTagMissing(_) => fix44::SessionRejectReason::RequiredTagMissing,
+            Self::InvalidTagNumber(_) => fix44::SessionRejectReason::InvalidTagNumber,
+            Self::TagNotDefinedForMessageType(_) => {
+                fix44::SessionRejectReason::TagNotDefinedForThisMessageType
+            }
+            Self::TagSpecifiedWithoutValue(_) => {
+                fix44::SessionRejectReason::TagSpecifiedWithoutAValue
+            }
+            Self::ValueIsIncorrect(_) => fix44::SessionRejectReason::ValueIsIncorrect,
+            Self::IncorrectDataFormat(_) => fix44::SessionRejectReason::IncorrectDataFormatForValue,
+            Self::TagSpecifiedOutOfOrder(_) => {
+                fix44::SessionRejectReason::TagSpecifiedOutOfRequiredOrder
+            }
+            Self::IncorrectNumInGroupCount(_) => {
+                fix44::SessionRejectReason::IncorrectNumingroupCountForRepeatingGroup
+            }
+        }
+    }
+
+    /// The tag that caused the error, to be sent as RefTagID(371).
+    pub fn tag(&self) -> Option<TagU32> {
+        match self {
+            Self::InvalidMsgType(_) => None,
+            Self::RequiredTagMissing(tag)
+            | Self::InvalidTagNumber(tag)
+            | Self::TagNotDefinedForMessageType(tag)
+            | Self::TagSpecifiedWithoutValue(tag)
+            | Self::ValueIsIncorrect(tag)
+            | Self::IncorrectDataFormat(tag)
+            | Self::TagSpecifiedOutOfOrder(tag)
+            | Self::IncorrectNumInGroupCount(tag) => Some(*tag),
+        }
+    }
+}
+
+/// Checks messages against the fields, groups and values defined in a dictionary.
+pub struct MessageValidator<'a> {
+    transport_dict: &'a Dictionary,
+    application_dict: &'a Dictionary,
+    settings: &'a ValidationSettings,
+}
+
+impl<'a> MessageValidator<'a> {
+    pub fn new(dict: &'a Dictionary, settings: &'a ValidationSettings) -> Self {
+        Self::with_dictionaries(dict, dict, settings)
+    }
+
+    /// Creates a validator for FIXT sessions, where the header and trailer are defined by the
+    /// transport dictionary and the body by a separate application dictionary.
+    pub fn with_dictionaries(
+        transport_dict: &'a Dictionary,
+        application_dict: &'a Dictionary,
+        settings: &'a ValidationSettings,
+    ) -> Self {
+        Self {
+            transport_dict,
+            application_dict,
+            settings,
+        }
+    }
+
+    pub fn validate(&self, message: &Message) -> Result<(), ValidationError> {
+        let message_type: &str = message.header().get(fix44::MSG_TYPE).unwrap_or_default();
+        let definition = self
+            .application_dict
+            .message_by_msgtype(message_type)
+            .ok_or_else(|| ValidationError::InvalidMsgType(message_type.to_string()))?;
+
+        let header = self.transport_dict.component_by_name("StandardHeader");
+        let header_items: Vec<LayoutItem> = header.iter().flat_map(|c| c.items()).collect();
+        let trailer = self.transport_dict.component_by_name("StandardTrailer");
+        let trailer_items: Vec<LayoutItem> = trailer.iter().flat_map(|c| c.items()).collect();
+        let header_tags = collect_tags(&header_items);
+        let trailer_tags = collect_tags(&trailer_items);
+
+        if self.settings.validate_fields_out_of_order {
+            for tag in message.body.fields.fields.keys() {
+                if header_tags.contains(tag) || trailer_tags.contains(tag) {
+                    return Err(ValidationError::TagSpecifiedOutOfOrder(*tag));
+                }
+            }
+            for tag in message.trailer.fields.fields.keys() {
+                if !trailer_tags.contains(tag) {
+                    return Err(ValidationError::TagSpecifiedOutOfOrder(*tag));
+                }
+            }
+        }
+
+        let misplaced: HashSet<TagU32> = header_tags.union(&trailer_tags).copied().collect();
+        self.validate_fields(
+            self.transport_dict,
+            &header_items,
+            &message.header.fields,
+            &HashSet::new(),
+        )?;
+        let body_items: Vec<LayoutItem> = definition.layout().collect();
+        self.validate_fields(
+            self.application_dict,
+            &body_items,
+            &message.body.fields,
+            &misplaced,
+        )?;
+        self.validate_fields(
+            self.transport_dict,
+            &trailer_items,
+            &message.trailer.fields,
+            &HashSet::new(),
+        )
+    }
+
+    /// Validates the fields of a message part or group entry laid out as `items`,
+    /// skipping the `ignored` tags.
+    fn validate_fields(
+        &self,
+        dict: &Dictionary,
+        items: &[LayoutItem],
+        fields: &FieldMap,
+        ignored: &HashSet<TagU32>,
+    ) -> Result<(), ValidationError> {
+        let allowed = collect_tags(items);
+        for (tag, field) in &fields.fields {
+            if ignored.contains(tag) {
+                continue;
+            }
+            let Some(definition) = dict.field_by_tag(tag.get()) else {
+                if self.rejects_unknown(*tag) {
+                    return Err(ValidationError::InvalidTagNumber(*tag));
+                }
+                continue;
+            };
+            if !allowed.contains(tag) && self.rejects_unknown(*tag) {
+                return Err(ValidationError::TagNotDefinedForMessageType(*tag));
+            }
+            validate_value(&definition, &field.data)?;
+
+            if definition.fix_datatype() == FixDatatype::NumInGroup {
+                let count = <usize as FieldType>::deserialize(&field.data)
+                    .map_err(|_| ValidationError::IncorrectDataFormat(*tag))?;
+                let entries = fields.groups.get(tag).map(Vec::len).unwrap_or_default();
+                if count != entries {
+                    return Err(ValidationError::IncorrectNumInGroupCount(*tag));
+                }
+            }
+        }
+
+        check_required(items, fields)?;
+
+        for (tag, entries) in &fields.groups {
+            self.validate_group(dict, items, *tag, entries, ignored)?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates the entries of the group starting with `tag`, if it's laid out in `items`.
+    fn validate_group(
+        &self,
+        dict: &Dictionary,
+        items: &[LayoutItem],
+        tag: TagU32,
+        entries: &[RepeatingGroup],
+        ignored: &HashSet<TagU32>,
+    ) -> Result<(), ValidationError> {
+        for item in items {
+            match item.kind() {
+                LayoutItemKind::Group(field, group_items) if field.tag() == tag => {
+                    for entry in entries {
+                        self.validate_fields(dict, &group_items, entry.get_fields(), ignored)?;
+                    }
+                }
+                LayoutItemKind::Component(component) => {
+                    let items: Vec<LayoutItem> = component.items().collect();
+                    self.validate_group(dict, &items, tag, entries, ignored)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn rejects_unknown(&self, tag: TagU32) -> bool {
+        if tag.get() >= FIRST_USER_DEFINED_TAG {
+            self.settings.validate_user_defined_fields
+        } else {
+            !self.settings.allow_unknown_msg_fields
+        }
+    }
+}
+
+/// Collects the tags that may appear at the level of `items`, including those of components
+/// but not the fields inside groups.
+fn collect_tags(items: &[LayoutItem]) -> HashSet<TagU32> {
+    let mut tags = HashSet::new();
+    for item in items {
+        match item.kind() {
+            LayoutItemKind::Field(field) | LayoutItemKind::Group(field, _) => {
+                tags.insert(field.tag());
+            }
+            LayoutItemKind::Component(component) => {
+                let items: Vec<LayoutItem> = component.items().collect();
+                tags.extend(collect_tags(&items));
+            }
+        }
+    }
+    tags
+}
+
+fn check_required(items: &[LayoutItem], fields: &FieldMap) -> Result<(), ValidationError> {
+    for item in items.iter().filter(|item| item.required()) {
+        match item.kind() {
+            LayoutItemKind::Field(field) | LayoutItemKind::Group(field, _) => {
+                if fields.get_raw(field.tag()).is_none() {
+                    return Err(ValidationError::RequiredTagMissing(field.tag()));
+                }
+            }
+            LayoutItemKind::Component(component) => {
+                let items: Vec<LayoutItem> = component.items().collect();
+                check_required(&items, fields)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_value(
+    definition: &hotfix_dictionary::Field,
+    value: &[u8],
+) -> Result<(), ValidationError> {
+    let tag = definition.tag();
+    if value.is_empty() {
+        return Err(ValidationError::TagSpecifiedWithoutValue(tag));
+    }
+
+    let datatype = definition.fix_datatype();
+    if !has_valid_format(datatype, value) {
+        return Err(ValidationError::IncorrectDataFormat(tag));
+    }
+
+    if definition.enums().is_some() {
+        let is_allowed = |value: &[u8]| {
+            definition
+                .enums()
+                .is_some_and(|mut enums| enums.any(|e| e.value().as_bytes() == value))
+        };
+        let multiple_values = matches!(
+            datatype,
+            FixDatatype::MultipleCharValue | FixDatatype::MultipleStringValue
+        );
+        let valid = if multiple_values {
+            value.split(|b| *b == b' ').all(is_allowed)
+        } else {
+            is_allowed(value)
+        };
+        if !valid {
+            return Err(ValidationError::ValueIsIncorrect(tag));
+        }
+    }
+
+    Ok(())
+}
+
+fn has_valid_format(datatype: FixDatatype, value: &[u8]) -> bool {
+    match datatype {
+        FixDatatype::Int => is_integer(value.strip_prefix(b"-").unwrap_or(value)),
+        FixDatatype::Length
+        | FixDatatype::NumInGroup
+        | FixDatatype::SeqNum
+        | FixDatatype::TagNum
+        | FixDatatype::DayOfMonth => is_integer(value),
+        FixDatatype::Float
+        | FixDatatype::Amt
+        | FixDatatype::Price
+        | FixDatatype::PriceOffset
+        | FixDatatype::Qty
+        | FixDatatype::Percentage => is_decimal(value.strip_prefix(b"-").unwrap_or(value)),
+        FixDatatype::Char => value.len() == 1,
+        FixDatatype::Boolean => value == b"Y" || value == b"N",
+        FixDatatype::UtcTimestamp => Timestamp::deserialize(value).is_ok(),
+        FixDatatype::UtcTimeOnly => Time::deserialize(value).is_ok(),
+        FixDatatype::UtcDateOnly | FixDatatype::LocalMktDate => Date::deserialize(value).is_ok(),
+        FixDatatype::MonthYear => MonthYear::deserialize(value).is_ok(),
+        _ => true,
+    }
+}
+
+fn is_integer(value: &[u8]) -> bool {
+    !value.is_empty() && value.iter().all(u8::is_ascii_digit)
+}
+
+fn is_decimal(value: &[u8]) -> bool {
+    let mut parts = value.splitn(2, |b| *b == b'.');
+    let integer = parts.next().unwrap_or_default();
+    let fraction = parts.next().unwrap_or_default();
+    (!integer.is_empty() || !fraction.is_empty())
+        && integer.iter().chain(fraction).all(u8::is_ascii_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Config;
+    use hotfix_dictionary::Dictionary;
+
+    const NEWS: &str = "8=FIX.4.4|9=0|35=B|49=SENDER|56=TARGET|34=2|52=20231103-12:00:00|148=headline|33=1|58=line|10=000|";
+
+    fn validate(raw: &str, settings: &ValidationSettings) -> Result<(), ValidationError> {
+        let config = Config { separator: b'|' };
+        let dict = Dictionary::fix44();
+        let message = Message::from_bytes(&config, &dict, raw.as_bytes()).unwrap();
+        MessageValidator::new(&dict, settings).validate(&message)
+    }
+
+    fn tag(tag: u32) -> TagU32 {
+        TagU32::new(tag).unwrap()
+    }
+
+    #[test]
+    fn valid_message_passes() {
+        assert_eq!(validate(NEWS, &ValidationSettings::default()), Ok(()));
+    }
+
+    #[test]
+    fn missing_required_fields_and_groups_are_reported() {
+        let settings = ValidationSettings::default();
+        let without_headline = NEWS.replace("148=headline|", "");
+        assert_eq!(
+            validate(&without_headline, &settings),
+            Err(ValidationError::RequiredTagMissing(tag(148)))
+        );
+
+        let without_lines = NEWS.replace("33=1|58=line|", "");
+        assert_eq!(
+            validate(&without_lines, &settings),
+            Err(ValidationError::RequiredTagMissing(tag(33)))
+        );
+
+        let without_header_field = NEWS.replace("49=SENDER|", "");
+        assert_eq!(
+            validate(&without_header_field, &settings),
+            Err(ValidationError::RequiredTagMissing(tag(49)))
+        );
+    }
+
+    #[test]
+    fn undefined_tags_are_reported() {
+        let settings = ValidationSettings::default();
+        let undefined = NEWS.replace("148=headline|", "148=headline|4999=X|");
+        assert_eq!(
+            validate(&undefined, &settings),
+            Err(ValidationError::InvalidTagNumber(tag(4999)))
+        );
+
+        // Price(44) is defined, but not for news
+        let not_for_news = NEWS.replace("148=headline|", "148=headline|44=1.5|");
+        assert_eq!(
+            validate(&not_for_news, &settings),
+            Err(ValidationError::TagNotDefinedForMessageType(tag(44)))
+        );
+    }
+
+    #[test]
+    fn unknown_fields_can_be_allowed() {
+        let user_defined = NEWS.replace("148=headline|", "148=headline|5001=X|");
+        let settings = ValidationSettings {
+            validate_user_defined_fields: false,
+            ..Default::default()
+        };
+        assert_eq!(validate(&user_defined, &settings), Ok(()));
+        assert_eq!(
+            validate(&user_defined, &ValidationSettings::default()),
+            Err(ValidationError::InvalidTagNumber(tag(5001)))
+        );
+
+        let unknown = NEWS.replace("148=headline|", "148=headline|44=1.5|4999=X|");
+        let settings = ValidationSettings {
+            allow_unknown_msg_fields: true,
+            ..Default::default()
+        };
+        assert_eq!(validate(&unknown, &settings), Ok(()));
+    }
+
+    #[test]
+    fn values_are_checked() {
+        let settings = ValidationSettings::default();
+        let out_of_range = NEWS.replace("148=headline|", "148=headline|61=9|");
+        assert_eq!(
+            validate(&out_of_range, &settings),
+            Err(ValidationError::ValueIsIncorrect(tag(61)))
+        );
+
+        let bad_format = NEWS.replace("34=2|", "34=two|");
+        assert_eq!(
+            validate(&bad_format, &settings),
+            Err(ValidationError::IncorrectDataFormat(tag(34)))
+        );
+
+        let bad_timestamp = NEWS.replace("52=20231103-12:00:00|", "52=yesterday|");
+        assert_eq!(
+            validate(&bad_timestamp, &settings),
+            Err(ValidationError::IncorrectDataFormat(tag(52)))
+        );
+
+        let empty = NEWS.replace("148=headline|", "148=|");
+        assert_eq!(
+            validate(&empty, &settings),
+            Err(ValidationError::TagSpecifiedWithoutValue(tag(148)))
+        );
+    }
+
+    #[test]
+    fn group_counts_are_checked() {
+        let settings = ValidationSettings::default();
+        let too_many = NEWS.replace("33=1|", "33=2|");
+        assert_eq!(
+            validate(&too_many, &settings),
+            Err(ValidationError::IncorrectNumInGroupCount(tag(33)))
+        );
+
+        let two_lines = NEWS.replace("33=1|58=line|", "33=2|58=first|58=second|");
+        assert_eq!(validate(&two_lines, &settings), Ok(()));
+    }
+
+    #[test]
+    fn fields_out_of_order_are_reported_unless_disabled() {
+        let header_field_in_body = NEWS.replace("148=headline|", "148=headline|50=SUB|");
+        assert_eq!(
+            validate(&header_field_in_body, &ValidationSettings::default()),
+            Err(ValidationError::TagSpecifiedOutOfOrder(tag(50)))
+        );
+
+        let settings = ValidationSettings {
+            validate_fields_out_of_order: false,
+            ..Default::default()
+        };
+        assert_eq!(validate(&header_field_in_body, &settings), Ok(()));
+    }
+
+    #[test]
+    fn errors_carry_the_reject_reason() {
+        let error = ValidationError::IncorrectNumInGroupCount(tag(33));
+        assert_eq!(
+            error.session_reject_reason(),
+            fix44::SessionRejectReason::IncorrectNumingroupCountForRepeatingGroup
+        );
+        assert_eq!(error.tag(), Some(tag(33)));
+    }
+}
@@ -0,0 +1,383 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+use crate::actors::application::{Application, ApplicationRef};
+use crate::config::SessionConfig;
+use crate::message::parser::Parser;
+use crate::message::{fix44, FixMessage};
+use crate::session::{ConfigError, ConnectionType, Delivery, SessionError, SessionRef};
+use crate::store::MessageStore;
+use crate::transport::FixConnection;
+
+struct AcceptorSession<M> {
+    config: SessionConfig,
+    session: SessionRef<M>,
+}
+
+type Sessions<M> = Arc<RwLock<Vec<AcceptorSession<M>>>>;
+
+/// How long a new connection has to send its logon before it's dropped.
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many bytes a new connection can send before its logon, to cap the parser's buffer.
+const MAX_LOGON_LENGTH: usize = 64 * 1024;
+
+/// Accepts inbound FIX connections and runs server-side sessions for them.
+///
+/// Connections are routed to sessions based on the CompIDs of the peer's logon message.
+/// The connection host and port of the session configs are ignored, all sessions
+/// share the listening socket of the acceptor. Only plain TCP is supported for now.
+///
+/// Dropping the acceptor closes the listening socket, established connections are kept.
+pub struct Acceptor<M> {
+    local_addr: SocketAddr,
+    sessions: Sessions<M>,
+    accept_task: JoinHandle<()>,
+}
+
+impl<M: FixMessage> Acceptor<M> {
+    pub async fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let local_addr = listener.local_addr()?;
+        let sessions: Sessions<M> = Arc::new(RwLock::new(vec![]));
+
+        let accept_task = tokio::spawn(accept_connections(listener, sessions.clone()));
+
+        Ok(Self {
+            local_addr,
+            sessions,
+            accept_task,
+        })
+    }
+
+    /// Adds a session for the CompIDs of the config.
+    ///
+    /// Fails if the dictionaries of the config can't be loaded or its secrets can't be read.
+    pub async fn add_session(
+        &self,
+        config: SessionConfig,
+        application: impl Application<M>,
+        store: impl MessageStore + Send + Sync + 'static,
+    ) -> Result<(), ConfigError> {
+        let application_ref = ApplicationRef::new(application);
+        let session = SessionRef::new(
+            config.clone(),
+            ConnectionType::Acceptor,
+            application_ref,
+            store,
+        )?;
+
+        self.sessions
+            .write()
+            .await
+            .push(AcceptorSession { config, session });
+        Ok(())
+    }
+
+    /// Sends an application message on the given session, see [SessionRef::send_message].
+    ///
+    /// Fails with [SessionError::SessionNotFound] if there's no such session.
+    pub async fn send_message(
+        &self,
+        sender_comp_id: &str,
+        target_comp_id: &str,
+        msg: M,
+    ) -> Result<Delivery, SessionError> {
+        match find_session(&self.sessions, sender_comp_id, target_comp_id).await {
+            Some(session) => session.send_message(msg).await,
+            None => Err(SessionError::SessionNotFound {
+                sender_comp_id: sender_comp_id.to_string(),
+                target_comp_id: target_comp_id.to_string(),
+            }),
+        }
+    }
+
+    /// Sends an application message on the given session without waiting for it,
+    /// see [SessionRef::post_message].
+    ///
+    /// Fails with [SessionError::SessionNotFound] if there's no such session.
+    pub async fn post_message(
+        &self,
+        sender_comp_id: &str,
+        target_comp_id: &str,
+        msg: M,
+    ) -> Result<(), SessionError> {
+        match find_session(&self.sessions, sender_comp_id, target_comp_id).await {
+            Some(session) => session.post_message(msg),
+            None => Err(SessionError::SessionNotFound {
+                sender_comp_id: sender_comp_id.to_string(),
+                target_comp_id: target_comp_id.to_string(),
+            }),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl<M> Drop for Acceptor<M> {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+async fn find_session<M: FixMessage>(
+    sessions: &Sessions<M>,
+    sender_comp_id: &str,
+    target_comp_id: &str,
+) -> Option<SessionRef<M>> {
+    sessions
+        .read()
+        .await
+        .iter()
+        .find(|s| {
+            s.config.sender_comp_id == sender_comp_id && s.config.target_comp_id == target_comp_id
+        })
+        .map(|s| s.session.clone())
+}
+
+async fn accept_connections<M: FixMessage>(listener: TcpListener, sessions: Sessions<M>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer_addr)) => {
+                debug!(%peer_addr, "accepted connection");
+                tokio::spawn(handle_connection(stream, sessions.clone()));
+            }
+            Err(err) => {
+                warn!("failed to accept connection: {err}");
+            }
+        }
+    }
+}
+
+async fn handle_connection<M: FixMessage>(mut stream: TcpStream, sessions: Sessions<M>) {
+    let mut parser = Parser::default();
+    let mut received_length = 0;
+    let read_logon = async {
+        loop {
+            let mut buf = vec![];
+            match stream.read_buf(&mut buf).await {
+                Ok(0) => {
+                    debug!("connection closed before logon");
+                    return None;
+                }
+                Err(err) => {
+                    warn!("failed to read from connection: {err}");
+                    return None;
+                }
+                Ok(n) => {
+                    let messages = parser.parse(&buf);
+                    if !messages.is_empty() {
+                        return Some(messages);
+                    }
+                    received_length += n;
+                    if received_length > MAX_LOGON_LENGTH {
+                        warn!(received_length, "no logon in what the connection has sent");
+                        return None;
+                    }
+                }
+            }
+        }
+    };
+    let received = match timeout(LOGON_TIMEOUT, read_logon).await {
+        Ok(Some(received)) => received,
+        Ok(None) => return,
+        Err(_) => {
+            warn!("connection hasn't sent a logon in time");
+            return;
+        }
+    };
+
+    let logon = &received[0];
+    let field = |tag: u32| {
+        logon
+            .get_raw(tag)
+            .and_then(|value| std::str::from_utf8(value).ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    if field(fix44::MSG_TYPE.tag) != "A" {
+        warn!("first message on inbound connection isn't a logon: {logon}");
+        return;
+    }
+
+    // the peer's sender is our target and vice versa
+    let sender_comp_id = field(fix44::TARGET_COMP_ID.tag);
+    let target_comp_id = field(fix44::SENDER_COMP_ID.tag);
+    let Some(session) = find_session(&sessions, &sender_comp_id, &target_comp_id).await else {
+        warn!(
+            sender_comp_id,
+            target_comp_id, "received logon for unknown session - dropping connection"
+        );
+        return;
+    };
+
+    let conn = match FixConnection::accept(stream, session, parser, received).await {
+        Ok(conn) => conn,
+        Err(err) => {
+            warn!(
+                sender_comp_id,
+                target_comp_id, "failed to hand over connection: {err}"
+            );
+            return;
+        }
+    };
+    conn.run_until_disconnect().await;
+    debug!(sender_comp_id, target_comp_id, "inbound connection dropped");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::time::timeout;
+
+    use crate::acceptor::{Acceptor, MAX_LOGON_LENGTH};
+    use crate::config::SessionConfig;
+    use crate::initiator::Initiator;
+    use crate::session::{ConfigError, DictionaryError, SessionError, SessionEventKind};
+    use crate::store::in_memory::InMemoryMessageStore;
+    use crate::test_utils::{session_config, News, RecordingApplication};
+
+    #[tokio::test]
+    async fn test_sessions_with_unsupported_dictionaries_are_refused() {
+        let config = SessionConfig {
+            begin_string: "FIX.4.9".to_string(),
+            ..session_config("ACCEPTOR", "INITIATOR", 0)
+        };
+
+        let acceptor = Acceptor::<News>::bind("127.0.0.1:0").await.unwrap();
+        let (application, _) = RecordingApplication::new();
+        let result = acceptor
+            .add_session(config.clone(), application, InMemoryMessageStore::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(ConfigError::Dictionary(
+                DictionaryError::UnsupportedBeginString(_)
+            ))
+        ));
+
+        let (application, _) = RecordingApplication::new();
+        let result: Result<Initiator<News>, _> =
+            Initiator::new(config, application, InMemoryMessageStore::default()).await;
+        assert!(matches!(
+            result,
+            Err(ConfigError::Dictionary(
+                DictionaryError::UnsupportedBeginString(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_initiator_to_acceptor_loopback() {
+        let acceptor = Acceptor::bind("127.0.0.1:0").await.unwrap();
+        let port = acceptor.local_addr().port();
+
+        let (acceptor_application, mut acceptor_received) = RecordingApplication::new();
+        acceptor
+            .add_session(
+                session_config("ACCEPTOR", "INITIATOR", port),
+                acceptor_application,
+                InMemoryMessageStore::default(),
+            )
+            .await
+            .unwrap();
+
+        let (initiator_application, mut initiator_received) = RecordingApplication::new();
+        let initiator = Initiator::new(
+            session_config("INITIATOR", "ACCEPTOR", port),
+            initiator_application,
+            InMemoryMessageStore::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut events = initiator.subscribe();
+        loop {
+            let event = timeout(Duration::from_secs(5), events.recv()).await;
+            if event.unwrap().unwrap().kind == SessionEventKind::LoggedOn {
+                break;
+            }
+        }
+
+        let to_acceptor = News::new("hello acceptor");
+        initiator.send_message(to_acceptor.clone()).await.unwrap();
+        let received = timeout(Duration::from_secs(5), acceptor_received.recv())
+            .await
+            .unwrap();
+        assert_eq!(received, Some(to_acceptor));
+
+        let to_initiator = News::new("hello initiator");
+        acceptor
+            .send_message("ACCEPTOR", "INITIATOR", to_initiator.clone())
+            .await
+            .unwrap();
+        let received = timeout(Duration::from_secs(5), initiator_received.recv())
+            .await
+            .unwrap();
+        assert_eq!(received, Some(to_initiator));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connection_without_logon_is_dropped() {
+        let acceptor = Acceptor::<News>::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(acceptor.local_addr()).await.unwrap();
+
+        // the paused clock moves on to the logon timeout once everything is idle
+        let mut buf = vec![];
+        let read = stream.read_buf(&mut buf).await;
+        assert_eq!(read.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_sending_garbage_is_dropped() {
+        let acceptor = Acceptor::<News>::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(acceptor.local_addr()).await.unwrap();
+
+        let garbage = vec![b'x'; 2 * MAX_LOGON_LENGTH];
+        // the acceptor may close the connection before it's all written
+        let _ = stream.write_all(&garbage).await;
+
+        let mut buf = vec![];
+        let read = timeout(Duration::from_secs(5), stream.read_buf(&mut buf)).await;
+        assert!(matches!(read.unwrap(), Ok(0) | Err(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dropping_acceptor_closes_listening_socket() {
+        let acceptor = Acceptor::<News>::bind("127.0.0.1:0").await.unwrap();
+        let address = acceptor.local_addr();
+
+        drop(acceptor);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(TcpStream::connect(address).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sending_to_unknown_session_fails() {
+        let acceptor = Acceptor::<News>::bind("127.0.0.1:0").await.unwrap();
+
+        let result = acceptor
+            .send_message("ACCEPTOR", "NOBODY", News::new("lost"))
+            .await;
+
+        assert_eq!(
+            result.err(),
+            Some(SessionError::SessionNotFound {
+                sender_comp_id: "ACCEPTOR".to_string(),
+                target_comp_id: "NOBODY".to_string(),
+            })
+        );
+    }
+}
@@ -1,20 +1,67 @@
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tracing::error;
 
-use crate::message::FixMessage;
+use crate::message::{FixMessage, Message, MessageRejection, Reject};
+use crate::session::SessionError;
 
+/// The callbacks of the application using a session.
+///
+/// The session waits for some callbacks to return, such as the verdict of
+/// [Application::on_message_to_app], and the callbacks are called one at a time.
+/// So callbacks mustn't await session methods that wait for the session, such as
+/// `send_message`, or they deadlock. Use `post_message` to send messages from within callbacks.
 #[async_trait::async_trait]
 pub trait Application<M>: Send + Sync + 'static {
+    /// Called with application messages as they're sent to the peer.
     async fn on_message_from_app(&self, msg: M);
-    async fn on_message_to_app(&self, msg: M);
+    /// Called with messages received from the peer.
+    ///
+    /// `possible_duplicate` is set for messages sent with PossDupFlag(43)=Y, which the
+    /// application may have already processed under a different sequence number.
+    ///
+    /// Returning an error refuses the message, the session answers the peer
+    /// with a Reject, a BusinessMessageReject or a Logout depending on the rejection.
+    ///
+    /// The session waits for the verdict, replies must be sent with `post_message`.
+    async fn on_message_to_app(
+        &self,
+        msg: M,
+        possible_duplicate: bool,
+    ) -> Result<(), MessageRejection>;
+    /// Called when the peer rejects one of our messages at the session level.
+    ///
+    /// The rejected message is looked up from the store using `RefSeqNum(45)`,
+    /// it's only available if it was an application message we still have.
+    async fn on_reject(&self, reject: Reject, rejected_message: Option<M>);
+    /// Called when the session has logged out, messages must be sent with `post_message`.
     async fn on_logout(&mut self, reason: &str);
+
+    /// Called before an admin message (Logon, Heartbeat, Logout, etc.) is sent to the peer.
+    ///
+    /// The message can be amended, e.g. to add custom fields required by the venue.
+    async fn on_admin_message_to_peer(&self, _msg: &mut Message) {}
+
+    /// Called with admin messages received from the peer, after the session has processed them.
+    async fn on_admin_message_to_app(&self, _msg: &Message) {}
 }
 
-#[derive(Debug, Clone)]
 pub enum ApplicationMessage<M> {
-    #[allow(dead_code)]
     SendingMessage(M),
-    ReceivedMessage(M),
+    ReceivedMessage {
+        message: M,
+        possible_duplicate: bool,
+        responder: oneshot::Sender<Result<(), MessageRejection>>,
+    },
+    ReceivedReject {
+        reject: Reject,
+        rejected_message: Option<M>,
+    },
     LoggedOut(String),
+    SendingAdmin {
+        message: Message,
+        responder: oneshot::Sender<Message>,
+    },
+    ReceivedAdmin(Message),
 }
 
 #[derive(Clone)]
@@ -31,18 +78,44 @@ impl<M: FixMessage> ApplicationRef<M> {
         Self { sender }
     }
 
+    /// Notifies the application, the notification is dropped if the application has stopped.
     pub async fn send_message(&self, msg: ApplicationMessage<M>) {
+        if self.sender.send(msg).await.is_err() {
+            error!("application has stopped, dropping notification");
+        }
+    }
+
+    /// Lets the application amend an admin message before it's sent.
+    pub async fn prepare_admin_message(&self, message: Message) -> Result<Message, SessionError> {
+        let (responder, receiver) = oneshot::channel();
         self.sender
-            .send(msg)
+            .send(ApplicationMessage::SendingAdmin { message, responder })
             .await
-            .expect("be able to send message to app");
+            .map_err(|_| SessionError::ApplicationStopped)?;
+        receiver.await.map_err(|_| SessionError::ApplicationStopped)
     }
 
-    pub async fn send_logout(&self, reason: String) {
+    /// Passes a received message to the application and waits for it to be accepted or refused.
+    pub async fn deliver_message(
+        &self,
+        message: M,
+        possible_duplicate: bool,
+    ) -> Result<Result<(), MessageRejection>, SessionError> {
+        let (responder, receiver) = oneshot::channel();
         self.sender
-            .send(ApplicationMessage::LoggedOut(reason))
+            .send(ApplicationMessage::ReceivedMessage {
+                message,
+                possible_duplicate,
+                responder,
+            })
             .await
-            .expect("be able tell the app we have been logged out");
+            .map_err(|_| SessionError::ApplicationStopped)?;
+        receiver.await.map_err(|_| SessionError::ApplicationStopped)
+    }
+
+    pub async fn send_logout(&self, reason: String) {
+        self.send_message(ApplicationMessage::LoggedOut(reason))
+            .await;
     }
 }
 
@@ -68,12 +141,38 @@ where
             ApplicationMessage::SendingMessage(m) => {
                 self.application.on_message_from_app(m).await;
             }
-            ApplicationMessage::ReceivedMessage(m) => {
-                self.application.on_message_to_app(m).await;
+            ApplicationMessage::ReceivedMessage {
+                message,
+                possible_duplicate,
+                responder,
+            } => {
+                let result = self
+                    .application
+                    .on_message_to_app(message, possible_duplicate)
+                    .await;
+                let _ = responder.send(result);
+            }
+            ApplicationMessage::ReceivedReject {
+                reject,
+                rejected_message,
+            } => {
+                self.application.on_reject(reject, rejected_message).await;
             }
             ApplicationMessage::LoggedOut(reason) => {
                 self.application.on_logout(&reason).await;
             }
+            ApplicationMessage::SendingAdmin {
+                mut message,
+                responder,
+            } => {
+                self.application
+                    .on_admin_message_to_peer(&mut message)
+                    .await;
+                let _ = responder.send(message);
+            }
+            ApplicationMessage::ReceivedAdmin(message) => {
+                self.application.on_admin_message_to_app(&message).await;
+            }
         }
     }
 }
@@ -1,6 +1,7 @@
 use tokio::io::{AsyncRead, AsyncReadExt, ReadHalf};
+use tokio::select;
 use tokio::sync::oneshot;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::message::parser::Parser;
 use crate::message::FixMessage;
@@ -16,10 +17,39 @@ pub struct ReaderRef {
 impl ReaderRef {
     pub fn new<M: FixMessage>(
         reader: ReadHalf<impl AsyncRead + Send + 'static>,
+        connection_id: u64,
         session_ref: SessionRef<M>,
+        stop_signal: oneshot::Receiver<()>,
+    ) -> Self {
+        Self::with_parser(
+            reader,
+            connection_id,
+            session_ref,
+            Parser::default(),
+            stop_signal,
+        )
+    }
+
+    /// Creates a reader that continues with a parser that may already hold a partial message.
+    ///
+    /// `connection_id` is the one of the connection's writer, the session only acts on
+    /// disconnects of the connection it's using.
+    pub fn with_parser<M: FixMessage>(
+        reader: ReadHalf<impl AsyncRead + Send + 'static>,
+        connection_id: u64,
+        session_ref: SessionRef<M>,
+        parser: Parser,
+        stop_signal: oneshot::Receiver<()>,
     ) -> Self {
         let (dc_sender, dc_receiver) = oneshot::channel();
-        let actor = ReaderActor::new(reader, session_ref, dc_sender);
+        let actor = ReaderActor::new(
+            reader,
+            connection_id,
+            session_ref,
+            parser,
+            stop_signal,
+            dc_sender,
+        );
         tokio::spawn(run_reader(actor));
 
         Self {
@@ -28,27 +58,37 @@ impl ReaderRef {
     }
 
     pub async fn wait_for_disconnect(self) {
-        self.disconnect_signal
-            .await
-            .expect("not to drop signal prematurely");
+        // the signal is only dropped without being sent if the reader has stopped unexpectedly,
+        // we're disconnected either way
+        let _ = self.disconnect_signal.await;
     }
 }
 
 struct ReaderActor<M, R> {
     reader: ReadHalf<R>,
+    connection_id: u64,
     session_ref: SessionRef<M>,
+    parser: Parser,
+    /// Fired by the writer when the session closes the connection.
+    stop_signal: oneshot::Receiver<()>,
     dc_sender: oneshot::Sender<()>,
 }
 
 impl<M, R: AsyncRead> ReaderActor<M, R> {
     fn new(
         reader: ReadHalf<R>,
+        connection_id: u64,
         session_ref: SessionRef<M>,
+        parser: Parser,
+        stop_signal: oneshot::Receiver<()>,
         dc_sender: oneshot::Sender<()>,
     ) -> Self {
         Self {
             reader,
+            connection_id,
             session_ref,
+            parser,
+            stop_signal,
             dc_sender,
         }
     }
@@ -59,34 +99,37 @@ where
     M: FixMessage,
     R: AsyncRead,
 {
-    let mut parser = Parser::default();
-    loop {
+    let reason = 'read: loop {
         let mut buf = vec![];
 
-        match actor.reader.read_buf(&mut buf).await {
-            Ok(0) => {
-                actor
-                    .session_ref
-                    .disconnect("received EOF".to_string())
-                    .await;
-                break;
-            }
-            Err(err) => {
-                actor.session_ref.disconnect(err.to_string()).await;
-                break;
-            }
+        let result = select! {
+            result = actor.reader.read_buf(&mut buf) => result,
+            _ = &mut actor.stop_signal => break "connection closed by session".to_string(),
+        };
+
+        match result {
+            Ok(0) => break "received EOF".to_string(),
+            Err(err) => break err.to_string(),
             Ok(_) => {
-                let messages = parser.parse(&buf);
+                let messages = actor.parser.parse(&buf);
 
                 for msg in messages {
-                    actor.session_ref.new_fix_message_received(msg).await;
+                    if let Err(err) = actor.session_ref.new_fix_message_received(msg).await {
+                        warn!("dropping the connection: {err}");
+                        break 'read err.to_string();
+                    }
                 }
             }
         }
+    };
+    if let Err(err) = actor
+        .session_ref
+        .disconnect(actor.connection_id, reason)
+        .await
+    {
+        debug!("couldn't tell the session about the disconnect: {err}");
     }
     debug!("reader loop is shutting down");
-    actor
-        .dc_sender
-        .send(())
-        .expect("be able to signal disconnect");
+    // nobody is waiting for the signal if the connection has been dropped
+    let _ = actor.dc_sender.send(());
 }
@@ -1,65 +1,119 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::io::{AsyncWrite, AsyncWriteExt, WriteHalf};
-use tokio::sync::mpsc;
-use tracing::debug;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
 
 use crate::message::parser::RawFixMessage;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum WriterMessage {
-    SendMessage(RawFixMessage),
+    /// Write the message, notifying the sender once it has been flushed if asked to.
+    SendMessage(RawFixMessage, Option<oneshot::Sender<()>>),
     Disconnect,
 }
 
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Clone, Debug)]
 pub struct WriterRef {
     sender: mpsc::Sender<WriterMessage>,
+    connection_id: u64,
 }
 
 impl WriterRef {
-    pub fn new(writer: WriteHalf<impl AsyncWrite + Send + 'static>) -> Self {
+    pub fn new(
+        writer: WriteHalf<impl AsyncWrite + Send + 'static>,
+        reader_stop: oneshot::Sender<()>,
+    ) -> Self {
         let (sender, mailbox) = mpsc::channel(10);
-        let actor = WriterActor::new(writer, mailbox);
+        let actor = WriterActor::new(writer, mailbox, reader_stop);
         tokio::spawn(run_writer(actor));
 
-        Self { sender }
+        Self {
+            sender,
+            connection_id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Identifies the connection, so that the session can ignore events from connections it has dropped.
+    pub fn connection_id(&self) -> u64 {
+        self.connection_id
     }
 
-    pub async fn send_raw_message(&self, msg: RawFixMessage) {
-        self.sender
-            .send(WriterMessage::SendMessage(msg))
+    /// Writes the message, it's dropped if the connection has already been closed.
+    pub async fn send_raw_message(&self, msg: RawFixMessage, flushed: Option<oneshot::Sender<()>>) {
+        if self
+            .sender
+            .send(WriterMessage::SendMessage(msg, flushed))
             .await
-            .expect("be able to send message");
+            .is_err()
+        {
+            debug!("connection has been closed, dropping message");
+        }
     }
 
     pub async fn disconnect(&self) {
-        self.sender
-            .send(WriterMessage::Disconnect)
-            .await
-            .expect("be able to disconnect")
+        if self.sender.send(WriterMessage::Disconnect).await.is_err() {
+            debug!("connection has already been closed");
+        }
     }
 }
 
 struct WriterActor<W> {
     writer: WriteHalf<W>,
     mailbox: mpsc::Receiver<WriterMessage>,
+    reader_stop: Option<oneshot::Sender<()>>,
 }
 
 impl<W: AsyncWrite> WriterActor<W> {
-    fn new(writer: WriteHalf<W>, mailbox: mpsc::Receiver<WriterMessage>) -> Self {
-        Self { writer, mailbox }
+    fn new(
+        writer: WriteHalf<W>,
+        mailbox: mpsc::Receiver<WriterMessage>,
+        reader_stop: oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            writer,
+            mailbox,
+            reader_stop: Some(reader_stop),
+        }
     }
 
     async fn handle(&mut self, message: WriterMessage) -> bool {
         match message {
-            WriterMessage::SendMessage(fix_message) => {
-                self.writer
-                    .write_all(fix_message.as_bytes())
-                    .await
-                    .expect("logon message to send");
+            WriterMessage::SendMessage(fix_message, flushed) => {
+                if let Err(err) = self.writer.write_all(fix_message.as_bytes()).await {
+                    warn!("failed to write message, closing the connection: {err}");
+                    self.stop_reader();
+                    return false;
+                }
                 debug!("sent message: {}", fix_message);
+                if let Some(flushed) = flushed {
+                    if let Err(err) = self.writer.flush().await {
+                        warn!("failed to flush message, closing the connection: {err}");
+                        self.stop_reader();
+                        return false;
+                    }
+                    let _ = flushed.send(());
+                }
                 true
             }
-            WriterMessage::Disconnect => false,
+            WriterMessage::Disconnect => {
+                if let Err(err) = self.writer.shutdown().await {
+                    debug!("failed to shut down writer: {err}");
+                }
+                // the peer may never close its side, so we stop reading too
+                self.stop_reader();
+                false
+            }
+        }
+    }
+}
+
+impl<W> WriterActor<W> {
+    /// Stops the reader, which lets the session know the connection has been closed.
+    fn stop_reader(&mut self) {
+        if let Some(reader_stop) = self.reader_stop.take() {
+            let _ = reader_stop.send(());
         }
     }
 }
@@ -73,3 +127,36 @@ async fn run_writer<W: AsyncWrite>(mut actor: WriterActor<W>) {
 
     debug!("writer loop is shutting down");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::io::duplex;
+    use tokio::sync::oneshot;
+    use tokio::time::timeout;
+
+    use crate::actors::socket_writer::WriterRef;
+    use crate::message::parser::RawFixMessage;
+
+    #[tokio::test]
+    async fn test_write_failure_closes_the_connection() {
+        let (stream, peer) = duplex(1024);
+        let (_reader, writer) = tokio::io::split(stream);
+        let (reader_stop, stop_signal) = oneshot::channel();
+        let writer_ref = WriterRef::new(writer, reader_stop);
+        drop(peer);
+
+        let (flushed, flushed_receiver) = oneshot::channel();
+        let message = RawFixMessage::new(b"8=FIX.4.4\x019=5\x0135=0\x0110=000\x01".to_vec());
+        writer_ref.send_raw_message(message, Some(flushed)).await;
+
+        // the reader is told to stop, which lets the session know we're disconnected
+        timeout(Duration::from_secs(1), stop_signal)
+            .await
+            .expect("the reader to be stopped")
+            .unwrap();
+        assert!(flushed_receiver.await.is_err());
+        // the writer has stopped, but sending to it doesn't panic
+        writer_ref.disconnect().await;
+    }
+}
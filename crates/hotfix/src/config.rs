@@ -1,6 +1,10 @@
-use serde::Deserialize;
-use std::fs;
-use std::path::Path;
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
+use hotfix_message::validation::ValidationSettings;
+use serde::{de, Deserialize, Deserializer};
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
@@ -19,16 +23,151 @@ pub struct TlsConfig {
     pub ca_certificate_path: String,
 }
 
+fn default_time_zone() -> Tz {
+    Tz::UTC
+}
+
+/// When the session is expected to be logged on.
+///
+/// Without a start and end day, the session runs daily between the start and end time.
+/// Otherwise it runs for a week from the start time on the start day until the end time
+/// on the end day. Times are interpreted in the given time zone, which defaults to UTC.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ScheduleConfig {
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub start_day: Option<Weekday>,
+    pub end_day: Option<Weekday>,
+    #[serde(default = "default_time_zone")]
+    pub time_zone: Tz,
+}
+
+/// The schedule fields as they appear among the other session fields, all of them optional
+/// so that sessions without a schedule can be told apart from incomplete schedules.
+#[derive(Deserialize)]
+struct ScheduleFields {
+    start_time: Option<NaiveTime>,
+    end_time: Option<NaiveTime>,
+    start_day: Option<Weekday>,
+    end_day: Option<Weekday>,
+    time_zone: Option<Tz>,
+}
+
+/// Reads the flattened schedule, failing on incomplete schedules rather than ignoring them.
+fn deserialize_schedule<'de, D>(deserializer: D) -> Result<Option<ScheduleConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fields = ScheduleFields::deserialize(deserializer)?;
+    match fields {
+        ScheduleFields {
+            start_time: None,
+            end_time: None,
+            start_day: None,
+            end_day: None,
+            time_zone: None,
+        } => Ok(None),
+        ScheduleFields {
+            start_time: Some(start_time),
+            end_time: Some(end_time),
+            start_day,
+            end_day,
+            time_zone,
+        } if start_day.is_some() == end_day.is_some() => Ok(Some(ScheduleConfig {
+            start_time,
+            end_time,
+            start_day,
+            end_day,
+            time_zone: time_zone.unwrap_or_else(default_time_zone),
+        })),
+        _ => Err(de::Error::custom(
+            "incomplete schedule, start_time and end_time are required, and start_day and end_day have to be given together",
+        )),
+    }
+}
+
+/// What happens to application messages sent while the session isn't logged on.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboundPolicy {
+    /// Keep the messages in memory and send them once we have logged on.
+    #[default]
+    Queue,
+    /// Drop the messages without using up a sequence number.
+    Reject,
+    /// Assign sequence numbers and persist the messages straight away,
+    /// then resend them as possible duplicates once we have logged on.
+    ///
+    /// The messages are lost if the sequence numbers are reset on logon.
+    ResendAsPossDup,
+}
+
+/// Where a secret such as a password is read from.
+///
+/// It can be given in the config as a plain string, or as `{ env = "VAR" }` or `{ file = "path" }`
+/// to keep it out of the config file. The secret is read again each time it's used, so it can be
+/// rotated without restarting the session.
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum SecretSource {
+    Plain(String),
+    Env { env: String },
+    File { file: PathBuf },
+}
+
+impl SecretSource {
+    pub fn resolve(&self) -> io::Result<String> {
+        match self {
+            Self::Plain(value) => Ok(value.clone()),
+            Self::Env { env } => env::var(env).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("environment variable {env}: {err}"),
+                )
+            }),
+            Self::File { file } => Ok(fs::read_to_string(file)?.trim_end().to_string()),
+        }
+    }
+}
+
+impl Debug for SecretSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plain(_) => write!(f, "Plain(***)"),
+            Self::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+            Self::File { file } => f.debug_struct("File").field("file", file).finish(),
+        }
+    }
+}
+
 fn default_reconnect_interval() -> u64 {
     30
 }
 
+fn default_logout_timeout() -> u64 {
+    10
+}
+
+fn default_max_latency() -> u64 {
+    120
+}
+
+fn default_resend_chunk_size() -> usize {
+    1000
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct SessionConfig {
     pub begin_string: String,
     pub sender_comp_id: String,
     pub target_comp_id: String,
-    pub data_dictionary_path: String,
+    /// A QuickFIX XML spec to use instead of the built-in dictionary, for venues with custom
+    /// fields or groups. FIXT.1.1 sessions use it for application messages of the default version.
+    #[serde(default)]
+    pub data_dictionary_path: Option<PathBuf>,
+    /// A QuickFIX XML spec to use instead of the built-in FIXT.1.1 dictionary in FIXT sessions.
+    #[serde(default)]
+    pub transport_data_dictionary_path: Option<PathBuf>,
     pub connection_host: String,
     pub connection_port: u16,
     #[serde(flatten)]
@@ -36,12 +175,54 @@ pub struct SessionConfig {
     pub heartbeat_interval: u64, // in seconds
     #[serde(default = "default_reconnect_interval")]
     pub reconnect_interval: u64, // in seconds
+    #[serde(default = "default_logout_timeout")]
+    pub logout_timeout: u64, // in seconds
+    /// How far SendingTime(52) of incoming messages may be from our clock.
+    #[serde(default = "default_max_latency")]
+    pub max_latency: u64, // in seconds
+    /// How many stored messages are loaded and resent at a time, so that long resends
+    /// don't hold up heartbeats and other session work.
+    #[serde(default = "default_resend_chunk_size")]
+    pub resend_chunk_size: usize,
+    /// Asks the peer to resend sequence gaps in ranges of at most this many messages,
+    /// like QuickFIX's ResendRequestChunkSize. The whole gap is asked for at once if it's 0.
+    #[serde(default)]
+    pub resend_request_chunk_size: u64,
     pub reset_on_logon: bool,
+    #[serde(default)]
+    pub outbound_policy: OutboundPolicy,
+    #[serde(flatten, deserialize_with = "deserialize_schedule")]
+    pub schedule: Option<ScheduleConfig>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<SecretSource>,
+    /// Sent along with the current password to change it on logon.
+    ///
+    /// Once a logon with it succeeds, it's sent as the password on later logons until the
+    /// session is restarted, so the config should be updated to the new password by then.
+    #[serde(default)]
+    pub new_password: Option<SecretSource>,
+    /// The ApplVerID(1128) of application messages in FIXT.1.1 sessions, such as "9" for
+    /// FIX 5.0 SP2, which is also the default. It's sent as DefaultApplVerID(1137) on logon.
+    #[serde(default)]
+    pub default_appl_ver_id: Option<String>,
+    /// Checks incoming messages against the data dictionary, including required fields,
+    /// enum values and repeating groups. Only undefined tags are rejected without it.
+    #[serde(default)]
+    pub validation: Option<ValidationSettings>,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{Config, TlsConfig};
+    use chrono::{NaiveTime, Weekday};
+    use chrono_tz::Tz;
+
+    use std::path::PathBuf;
+
+    use crate::config::{
+        Config, OutboundPolicy, ScheduleConfig, SecretSource, TlsConfig, ValidationSettings,
+    };
 
     #[test]
     fn test_simple_config() {
@@ -62,11 +243,15 @@ reset_on_logon = false
         let config: Config = toml::from_str(config_contents).unwrap();
         assert_eq!(config.sessions.len(), 1);
 
-        let session_config = config.sessions.get(0).unwrap();
+        let session_config = config.sessions.first().unwrap();
         assert_eq!(session_config.begin_string, "FIX.4.4");
         assert_eq!(session_config.sender_comp_id, "send-comp-id");
         assert_eq!(session_config.target_comp_id, "target-comp-id");
-        assert_eq!(session_config.data_dictionary_path, "./spec/FIX44.xml");
+        assert_eq!(
+            session_config.data_dictionary_path,
+            Some(PathBuf::from("./spec/FIX44.xml"))
+        );
+        assert_eq!(session_config.transport_data_dictionary_path, None);
         assert_eq!(session_config.connection_port, 443);
         assert_eq!(session_config.connection_host, "127.0.0.1");
         assert_eq!(session_config.heartbeat_interval, 30);
@@ -75,5 +260,171 @@ reset_on_logon = false
         };
         assert_eq!(session_config.tls_config, Some(expected_tls_config));
         assert_eq!(session_config.reconnect_interval, 30);
+        assert_eq!(session_config.logout_timeout, 10);
+        assert_eq!(session_config.max_latency, 120);
+        assert_eq!(session_config.resend_chunk_size, 1000);
+        assert_eq!(session_config.resend_request_chunk_size, 0);
+        assert_eq!(session_config.schedule, None);
+        assert_eq!(session_config.outbound_policy, OutboundPolicy::Queue);
+        assert_eq!(session_config.username, None);
+        assert_eq!(session_config.password, None);
+        assert_eq!(session_config.new_password, None);
+        assert_eq!(session_config.validation, None);
+    }
+
+    #[test]
+    fn test_config_with_incomplete_schedule_fails() {
+        let config_contents = r#"
+[[sessions]]
+begin_string = "FIX.4.4"
+sender_comp_id = "send-comp-id"
+target_comp_id = "target-comp-id"
+
+connection_port = 443
+connection_host = "127.0.0.1"
+heartbeat_interval = 30
+reset_on_logon = false
+
+start_time = "17:05:00"
+start_day = "Sunday"
+end_day = "Friday"
+        "#;
+
+        let err = toml::from_str::<Config>(config_contents).unwrap_err();
+        assert!(err.to_string().contains("incomplete schedule"));
+    }
+
+    #[test]
+    fn test_config_with_malformed_schedule_fails() {
+        let config_contents = r#"
+[[sessions]]
+begin_string = "FIX.4.4"
+sender_comp_id = "send-comp-id"
+target_comp_id = "target-comp-id"
+
+connection_port = 443
+connection_host = "127.0.0.1"
+heartbeat_interval = 30
+reset_on_logon = false
+
+start_time = "17:05:00"
+end_time = "5pm"
+        "#;
+
+        assert!(toml::from_str::<Config>(config_contents).is_err());
+    }
+
+    #[test]
+    fn test_config_with_schedule() {
+        let config_contents = r#"
+[[sessions]]
+begin_string = "FIX.4.4"
+sender_comp_id = "send-comp-id"
+target_comp_id = "target-comp-id"
+data_dictionary_path = "./spec/FIX44.xml"
+
+connection_port = 443
+connection_host = "127.0.0.1"
+heartbeat_interval = 30
+reset_on_logon = false
+
+start_time = "17:05:00"
+end_time = "17:00:00"
+start_day = "Sunday"
+end_day = "Friday"
+time_zone = "America/New_York"
+outbound_policy = "resend_as_poss_dup"
+        "#;
+
+        let config: Config = toml::from_str(config_contents).unwrap();
+        let session_config = config.sessions.first().unwrap();
+
+        let expected_schedule = ScheduleConfig {
+            start_time: NaiveTime::from_hms_opt(17, 5, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            start_day: Some(Weekday::Sun),
+            end_day: Some(Weekday::Fri),
+            time_zone: Tz::America__New_York,
+        };
+        assert_eq!(session_config.schedule, Some(expected_schedule));
+        assert_eq!(
+            session_config.outbound_policy,
+            OutboundPolicy::ResendAsPossDup
+        );
+    }
+
+    #[test]
+    fn test_config_with_credentials() {
+        let config_contents = r#"
+[[sessions]]
+begin_string = "FIX.4.4"
+sender_comp_id = "send-comp-id"
+target_comp_id = "target-comp-id"
+data_dictionary_path = "./spec/FIX44.xml"
+
+connection_port = 443
+connection_host = "127.0.0.1"
+heartbeat_interval = 30
+reset_on_logon = false
+
+username = "user"
+password = { env = "FIX_PASSWORD" }
+new_password = { file = "/run/secrets/fix-new-password" }
+        "#;
+
+        let config: Config = toml::from_str(config_contents).unwrap();
+        let session_config = config.sessions.first().unwrap();
+
+        assert_eq!(session_config.username.as_deref(), Some("user"));
+        assert_eq!(
+            session_config.password,
+            Some(SecretSource::Env {
+                env: "FIX_PASSWORD".to_string()
+            })
+        );
+        assert_eq!(
+            session_config.new_password,
+            Some(SecretSource::File {
+                file: PathBuf::from("/run/secrets/fix-new-password")
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_with_validation() {
+        let config_contents = r#"
+[[sessions]]
+begin_string = "FIX.4.4"
+sender_comp_id = "send-comp-id"
+target_comp_id = "target-comp-id"
+
+connection_port = 443
+connection_host = "127.0.0.1"
+heartbeat_interval = 30
+reset_on_logon = false
+
+[sessions.validation]
+allow_unknown_msg_fields = true
+        "#;
+
+        let config: Config = toml::from_str(config_contents).unwrap();
+        let session_config = config.sessions.first().unwrap();
+
+        assert_eq!(
+            session_config.validation,
+            Some(ValidationSettings {
+                validate_fields_out_of_order: true,
+                validate_user_defined_fields: true,
+                allow_unknown_msg_fields: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_plain_secret_is_not_shown_in_debug_output() {
+        let secret = SecretSource::Plain("hunter2".to_string());
+
+        assert_eq!(secret.resolve().unwrap(), "hunter2");
+        assert_eq!(format!("{:?}", secret), "Plain(***)");
     }
 }
@@ -1,11 +1,15 @@
+use chrono::Utc;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::time::sleep;
-use tracing::{debug, warn};
+use tracing::{debug, error, warn};
 
 use crate::actors::application::{Application, ApplicationRef};
 use crate::config::SessionConfig;
 use crate::message::FixMessage;
-use crate::session::SessionRef;
+use crate::session::{
+    ConfigError, ConnectionType, Delivery, SessionError, SessionEvent, SessionRef,
+};
 use crate::store::MessageStore;
 use crate::transport::FixConnection;
 
@@ -15,13 +19,21 @@ pub struct Initiator<M> {
 }
 
 impl<M: FixMessage> Initiator<M> {
+    /// Starts the session and keeps connecting to the peer.
+    ///
+    /// Fails if the dictionaries of the config can't be loaded or its secrets can't be read.
     pub async fn new(
         config: SessionConfig,
         application: impl Application<M>,
         store: impl MessageStore + Send + Sync + 'static,
-    ) -> Self {
+    ) -> Result<Self, ConfigError> {
         let application_ref = ApplicationRef::new(application);
-        let session_ref = SessionRef::new(config.clone(), application_ref, store);
+        let session_ref = SessionRef::new(
+            config.clone(),
+            ConnectionType::Initiator,
+            application_ref,
+            store,
+        )?;
 
         tokio::spawn({
             let config = config.clone();
@@ -29,14 +41,53 @@ impl<M: FixMessage> Initiator<M> {
             establish_connection(config, session_ref)
         });
 
-        Self {
+        Ok(Self {
             config,
             session: session_ref,
-        }
+        })
+    }
+
+    /// Subscribes to the session's events, such as logons and disconnects.
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.session.subscribe()
+    }
+
+    /// Sends an application message, see [SessionRef::send_message].
+    pub async fn send_message(&self, msg: M) -> Result<Delivery, SessionError> {
+        self.session.send_message(msg).await
+    }
+
+    /// Sends an application message without waiting for it, see [SessionRef::post_message].
+    pub fn post_message(&self, msg: M) -> Result<(), SessionError> {
+        self.session.post_message(msg)
+    }
+
+    /// Logs out of the session and stops reconnecting.
+    ///
+    /// Waits for the peer to acknowledge the logout, or for the logout timeout to expire,
+    /// and returns once the connection has been closed.
+    pub async fn logout(&self, reason: &str) -> Result<(), SessionError> {
+        self.session.logout(reason.to_string()).await
+    }
+
+    /// Sets the sequence number of the next message we send.
+    pub async fn set_next_sender_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.session.set_next_sender_seq_num(seq_num).await
     }
 
-    pub async fn send_message(&self, msg: M) {
-        self.session.send_message(msg).await;
+    /// Sets the sequence number we expect on the next message from the peer.
+    pub async fn set_next_target_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.session.set_next_target_seq_num(seq_num).await
+    }
+
+    /// Sends a SequenceReset(4) in reset mode, see [SessionRef::send_sequence_reset].
+    pub async fn send_sequence_reset(&self, new_seq_no: u64) -> Result<(), SessionError> {
+        self.session.send_sequence_reset(new_seq_no).await
+    }
+
+    /// Asks the peer to resend the given range, an `end` of 0 means everything from `begin` onwards.
+    pub async fn send_resend_request(&self, begin: u64, end: u64) -> Result<(), SessionError> {
+        self.session.send_resend_request(begin, end).await
     }
 
     pub fn is_interested(&self, sender_comp_id: &str, target_comp_id: &str) -> bool {
@@ -46,14 +97,48 @@ impl<M: FixMessage> Initiator<M> {
 
 async fn establish_connection<M: FixMessage>(config: SessionConfig, session_ref: SessionRef<M>) {
     loop {
-        if !session_ref.should_reconnect().await {
-            warn!("session indicated we shouldn't reconnect");
-            break;
+        match session_ref.should_reconnect().await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("session indicated we shouldn't reconnect");
+                break;
+            }
+            Err(err) => {
+                error!("not reconnecting: {err}");
+                break;
+            }
+        }
+
+        if let Some(schedule) = &config.schedule {
+            let now = Utc::now();
+            if !schedule.is_in_session(now) {
+                let wait = (schedule.next_transition(now) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                debug!(
+                    "outside of session time, waiting {} seconds to connect",
+                    wait.as_secs()
+                );
+                sleep(wait).await;
+                continue;
+            }
         }
 
         match FixConnection::connect(&config, session_ref.clone()).await {
             Ok(conn) => {
-                session_ref.register_writer(conn.get_writer()).await;
+                match session_ref.register_writer(conn.get_writer()).await {
+                    Ok(()) => {}
+                    Err(
+                        err @ (SessionError::AlreadyConnected | SessionError::OutsideSessionTime),
+                    ) => {
+                        // the session has closed the connection, we try again later
+                        warn!("connection refused by the session: {err}");
+                    }
+                    Err(err) => {
+                        error!("not reconnecting: {err}");
+                        break;
+                    }
+                }
                 conn.run_until_disconnect().await;
 
                 warn!("session connection dropped, attempting to reconnect");
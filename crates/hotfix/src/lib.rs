@@ -1,3 +1,4 @@
+pub mod acceptor;
 mod actors;
 pub mod config;
 pub mod initiator;
@@ -5,6 +6,8 @@ pub mod message;
 mod message_utils;
 pub mod session;
 pub mod store;
+#[cfg(test)]
+mod test_utils;
 pub(crate) mod transport;
 
 pub use actors::application::Application;
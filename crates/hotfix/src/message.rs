@@ -1,29 +1,52 @@
 // re-exposing these as applications need this to define their own messages
+pub use business_reject::BusinessReject;
 pub use hotfix_message::field_types::Timestamp;
-pub use hotfix_message::fix44;
 pub use hotfix_message::message::{Config, Message};
+pub use hotfix_message::{fix42, fix44, fixt11};
 pub use hotfix_message::{Part, RepeatingGroup};
+pub use reject::Reject;
+pub use rejection::MessageRejection;
 
+pub(crate) mod business_reject;
 pub(crate) mod heartbeat;
 pub(crate) mod logon;
+pub(crate) mod logout;
 pub(crate) mod parser;
+pub(crate) mod reject;
+pub(crate) mod rejection;
+pub(crate) mod resend_request;
 pub(crate) mod sequence_reset;
+pub(crate) mod test_request;
 
 pub trait FixMessage: Clone + Send + 'static {
     fn write(&self, msg: &mut Message);
 
     fn message_type(&self) -> &str;
 
-    fn parse(message: &Message) -> Self;
+    /// Parses a received message.
+    ///
+    /// Messages that can't be parsed are refused, the session answers the
+    /// counterparty according to the returned rejection.
+    fn parse(message: &Message) -> Result<Self, MessageRejection>;
+
+    /// Whether messages of the given type are handled by the application.
+    ///
+    /// Messages of other types are answered with a BusinessMessageReject
+    /// instead of being parsed and passed on to the application.
+    fn is_supported(message_type: &str) -> bool {
+        let _ = message_type;
+        true
+    }
 }
 
-pub(crate) fn generate_message(
+pub(crate) fn build_message(
+    begin_string: &str,
     sender_comp_id: &str,
     target_comp_id: &str,
     msg_seq_num: usize,
     message: impl FixMessage,
-) -> Vec<u8> {
-    let mut msg = Message::new("FIX.4.4", message.message_type());
+) -> Message {
+    let mut msg = Message::new(begin_string, message.message_type());
     msg.set(fix44::SENDER_COMP_ID, sender_comp_id);
     msg.set(fix44::TARGET_COMP_ID, target_comp_id.as_bytes());
     msg.set(fix44::MSG_SEQ_NUM, msg_seq_num);
@@ -31,7 +54,7 @@ pub(crate) fn generate_message(
 
     message.write(&mut msg);
 
-    msg.encode(&Config::default())
+    msg
 }
 
 pub trait WriteMessage {
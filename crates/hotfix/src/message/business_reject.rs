@@ -0,0 +1,84 @@
+use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
+
+use crate::message::{FixMessage, MessageRejection};
+
+/// An application-level reject, sent when a message passed the session checks
+/// but can't be processed by the application.
+#[derive(Clone, Debug)]
+pub struct BusinessReject {
+    pub ref_seq_num: Option<u64>,
+    pub ref_msg_type: String,
+    pub business_reject_ref_id: Option<String>,
+    pub business_reject_reason: fix44::BusinessRejectReason,
+    pub text: Option<String>,
+}
+
+impl BusinessReject {
+    pub fn new(ref_msg_type: &str, reason: fix44::BusinessRejectReason) -> Self {
+        Self {
+            ref_seq_num: None,
+            ref_msg_type: ref_msg_type.to_string(),
+            business_reject_ref_id: None,
+            business_reject_reason: reason,
+            text: None,
+        }
+    }
+
+    pub fn with_ref_seq_num(mut self, ref_seq_num: u64) -> Self {
+        self.ref_seq_num = Some(ref_seq_num);
+        self
+    }
+
+    /// The business-level ID of the rejected message, such as its ClOrdID(11).
+    pub fn with_business_reject_ref_id(mut self, business_reject_ref_id: &str) -> Self {
+        self.business_reject_ref_id = Some(business_reject_ref_id.to_string());
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+}
+
+impl FixMessage for BusinessReject {
+    fn write(&self, msg: &mut Message) {
+        if let Some(ref_seq_num) = self.ref_seq_num {
+            msg.set(fix44::REF_SEQ_NUM, ref_seq_num);
+        }
+        msg.set(fix44::REF_MSG_TYPE, self.ref_msg_type.as_str());
+        if let Some(business_reject_ref_id) = &self.business_reject_ref_id {
+            msg.set(
+                fix44::BUSINESS_REJECT_REF_ID,
+                business_reject_ref_id.as_str(),
+            );
+        }
+        msg.set(fix44::BUSINESS_REJECT_REASON, self.business_reject_reason);
+        if let Some(text) = &self.text {
+            msg.set(fix44::TEXT, text.as_str());
+        }
+    }
+
+    fn message_type(&self) -> &str {
+        "j"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        Ok(Self {
+            ref_seq_num: message.get(fix44::REF_SEQ_NUM).ok(),
+            ref_msg_type: message
+                .get::<&str>(fix44::REF_MSG_TYPE)
+                .unwrap_or_default()
+                .to_string(),
+            business_reject_ref_id: message
+                .get::<&str>(fix44::BUSINESS_REJECT_REF_ID)
+                .ok()
+                .map(|id| id.to_string()),
+            business_reject_reason: message
+                .get(fix44::BUSINESS_REJECT_REASON)
+                .unwrap_or(fix44::BusinessRejectReason::Other),
+            text: message.get::<&str>(fix44::TEXT).ok().map(|t| t.to_string()),
+        })
+    }
+}
@@ -1,18 +1,38 @@
 use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
 
-use crate::message::FixMessage;
+use crate::message::{FixMessage, MessageRejection};
 
-#[derive(Clone, Debug)]
-pub struct Heartbeat;
+#[derive(Clone, Debug, Default)]
+pub struct Heartbeat {
+    test_req_id: Option<String>,
+}
+
+impl Heartbeat {
+    /// Creates a heartbeat in response to a test request.
+    pub fn for_test_request(test_req_id: String) -> Self {
+        Self {
+            test_req_id: Some(test_req_id),
+        }
+    }
+}
 
 impl FixMessage for Heartbeat {
-    fn write(&self, _msg: &mut Message) {}
+    fn write(&self, msg: &mut Message) {
+        if let Some(test_req_id) = &self.test_req_id {
+            msg.set(fix44::TEST_REQ_ID, test_req_id.as_str());
+        }
+    }
 
     fn message_type(&self) -> &str {
         "0"
     }
 
-    fn parse(_message: &Message) -> Self {
-        Heartbeat {}
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        let test_req_id = message
+            .get::<&str>(fix44::TEST_REQ_ID)
+            .ok()
+            .map(|id| id.to_string());
+        Ok(Self { test_req_id })
     }
 }
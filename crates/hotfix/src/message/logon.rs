@@ -1,14 +1,37 @@
+use std::fmt::{Debug, Formatter};
+
 use hotfix_message::message::Message;
-use hotfix_message::{fix44, Part};
+use hotfix_message::{fix44, fixt11, Part};
 
-use crate::message::FixMessage;
+use crate::message::{FixMessage, MessageRejection};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Logon {
     encrypt_method: fix44::EncryptMethod,
     heartbeat_interval: u64,
     reset_seq_num_flag: fix44::ResetSeqNumFlag,
     next_expected_msg_seq_num: Option<u64>,
+    username: Option<String>,
+    password: Option<String>,
+    new_password: Option<String>,
+    default_appl_ver_id: Option<fixt11::ApplVerId>,
+}
+
+/// Passwords are redacted so logons can be logged.
+impl Debug for Logon {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redacted = |secret: &Option<String>| secret.as_ref().map(|_| "***");
+        f.debug_struct("Logon")
+            .field("encrypt_method", &self.encrypt_method)
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .field("reset_seq_num_flag", &self.reset_seq_num_flag)
+            .field("next_expected_msg_seq_num", &self.next_expected_msg_seq_num)
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("new_password", &redacted(&self.new_password))
+            .field("default_appl_ver_id", &self.default_appl_ver_id)
+            .finish()
+    }
 }
 
 pub enum ResetSeqNumConfig {
@@ -27,8 +50,34 @@ impl Logon {
             heartbeat_interval,
             reset_seq_num_flag,
             next_expected_msg_seq_num,
+            username: None,
+            password: None,
+            new_password: None,
+            default_appl_ver_id: None,
         }
     }
+
+    pub fn with_username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+
+    pub fn with_password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// Asks the counterparty to replace the current password with a new one.
+    pub fn with_new_password(mut self, new_password: &str) -> Self {
+        self.new_password = Some(new_password.to_string());
+        self
+    }
+
+    /// Sets the version of application messages in FIXT sessions.
+    pub fn with_default_appl_ver_id(mut self, appl_ver_id: fixt11::ApplVerId) -> Self {
+        self.default_appl_ver_id = Some(appl_ver_id);
+        self
+    }
 }
 
 impl FixMessage for Logon {
@@ -40,13 +89,66 @@ impl FixMessage for Logon {
         if let Some(next) = self.next_expected_msg_seq_num {
             msg.set(fix44::NEXT_EXPECTED_MSG_SEQ_NUM, next);
         }
+        if let Some(username) = &self.username {
+            msg.set(fix44::USERNAME, username.as_str());
+        }
+        if let Some(password) = &self.password {
+            msg.set(fix44::PASSWORD, password.as_str());
+        }
+        if let Some(new_password) = &self.new_password {
+            msg.set(fix44::NEW_PASSWORD, new_password.as_str());
+        }
+        if let Some(appl_ver_id) = self.default_appl_ver_id {
+            msg.set(fixt11::DEFAULT_APPL_VER_ID, appl_ver_id);
+        }
     }
 
     fn message_type(&self) -> &str {
         "A"
     }
 
-    fn parse(_message: &Message) -> Self {
-        todo!()
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        let optional_string = |field| {
+            message
+                .get::<&str>(field)
+                .ok()
+                .map(|value| value.to_string())
+        };
+        Ok(Self {
+            encrypt_method: message
+                .get(fix44::ENCRYPT_METHOD)
+                .map_err(|_| MessageRejection::missing_field(fix44::ENCRYPT_METHOD))?,
+            heartbeat_interval: message
+                .get(fix44::HEART_BT_INT)
+                .map_err(|_| MessageRejection::missing_field(fix44::HEART_BT_INT))?,
+            reset_seq_num_flag: message
+                .get(fix44::RESET_SEQ_NUM_FLAG)
+                .unwrap_or(fix44::ResetSeqNumFlag::No),
+            next_expected_msg_seq_num: message.get(fix44::NEXT_EXPECTED_MSG_SEQ_NUM).ok(),
+            username: optional_string(fix44::USERNAME),
+            password: optional_string(fix44::PASSWORD),
+            new_password: optional_string(fix44::NEW_PASSWORD),
+            default_appl_ver_id: message.get(fixt11::DEFAULT_APPL_VER_ID).ok(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_passwords() {
+        let logon = Logon::new(30, ResetSeqNumConfig::Reset)
+            .with_username("trader")
+            .with_password("secret")
+            .with_new_password("new-secret");
+
+        let debug = format!("{logon:?}");
+
+        assert!(debug.contains(r#"username: Some("trader")"#));
+        assert!(debug.contains(r#"password: Some("***")"#));
+        assert!(debug.contains(r#"new_password: Some("***")"#));
+        assert!(!debug.contains("secret"));
     }
 }
@@ -0,0 +1,35 @@
+use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
+
+use crate::message::{FixMessage, MessageRejection};
+
+#[derive(Clone, Debug, Default)]
+pub struct Logout {
+    text: Option<String>,
+}
+
+impl Logout {
+    pub fn with_reason(reason: String) -> Self {
+        Self { text: Some(reason) }
+    }
+}
+
+impl FixMessage for Logout {
+    fn write(&self, msg: &mut Message) {
+        if let Some(text) = &self.text {
+            msg.set(fix44::TEXT, text.as_str());
+        }
+    }
+
+    fn message_type(&self) -> &str {
+        "5"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        let text = message
+            .get::<&str>(fix44::TEXT)
+            .ok()
+            .map(|text| text.to_string());
+        Ok(Self { text })
+    }
+}
@@ -1,9 +1,15 @@
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::ops::Range;
 
 const FIELD_CHECKSUM_LEN_IN_BYTES: usize = 7; // the checksum is always 7 bytes
 
-#[derive(Clone, Debug)]
+/// Fields whose values are never shown when messages are logged.
+const MASKED_TAGS: [&[u8]; 2] = [
+    b"554", // Password
+    b"925", // NewPassword
+];
+
+#[derive(Clone)]
 pub struct RawFixMessage {
     data: Vec<u8>,
 }
@@ -16,21 +22,48 @@ impl RawFixMessage {
     pub fn as_bytes(&self) -> &[u8] {
         &self.data
     }
+
+    /// Looks up the value of the first occurrence of `tag` without parsing the whole message.
+    pub fn get_raw(&self, tag: u32) -> Option<&[u8]> {
+        let prefix = format!("{tag}=");
+        self.data
+            .split(|b| *b == b'\x01')
+            .find_map(|field| field.strip_prefix(prefix.as_bytes()))
+    }
 }
 
 impl Display for RawFixMessage {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let pretty_bytes: Vec<u8> = self
-            .data
-            .iter()
-            .map(|b| if *b == b'\x01' { b'|' } else { *b })
-            .collect();
+        let mut pretty_bytes: Vec<u8> = Vec::with_capacity(self.data.len());
+        for field in self.data.split_inclusive(|b| *b == b'\x01') {
+            let field = field.strip_suffix(b"\x01").unwrap_or(field);
+            match field.iter().position(|b| *b == b'=') {
+                Some(i) if MASKED_TAGS.contains(&&field[..i]) => {
+                    pretty_bytes.extend_from_slice(&field[..=i]);
+                    pretty_bytes.extend_from_slice(b"***");
+                }
+                _ => pretty_bytes.extend_from_slice(field),
+            }
+            pretty_bytes.push(b'|');
+        }
+        // only the fields ending with a separator get one in the output
+        if !self.data.ends_with(b"\x01") {
+            pretty_bytes.pop();
+        }
         let s = std::str::from_utf8(&pretty_bytes).unwrap_or("invalid characters");
 
         write!(f, "{}", s)
     }
 }
 
+impl Debug for RawFixMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RawFixMessage")
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
 #[derive(Default)]
 pub struct Parser {
     buffer: Vec<u8>,
@@ -109,7 +142,7 @@ impl HeaderInfo {
 
 #[cfg(test)]
 mod tests {
-    use crate::message::parser::{HeaderInfo, Parser};
+    use crate::message::parser::{HeaderInfo, Parser, RawFixMessage};
 
     #[test]
     fn test_parsing_exact_message() {
@@ -148,6 +181,17 @@ mod tests {
         assert_eq!(parser.buffer.len(), 0);
     }
 
+    #[test]
+    fn test_raw_field_lookup() {
+        let data = b"8=FIX.4.4\x019=77\x0135=A\x0134=1\x0149=validus-fix\x0152=20230908-08:24:56.574\x0156=FXALL\x0198=0\x01108=30\x01141=Y\x0110=037\x01";
+        let message = RawFixMessage::new(data.to_vec());
+
+        assert_eq!(message.get_raw(35), Some(b"A".as_slice()));
+        assert_eq!(message.get_raw(49), Some(b"validus-fix".as_slice()));
+        assert_eq!(message.get_raw(56), Some(b"FXALL".as_slice()));
+        assert_eq!(message.get_raw(553), None);
+    }
+
     #[test]
     fn test_incomplete_header_info() {
         let data = b"8=FIX.4.4\x019";
@@ -173,4 +217,14 @@ mod tests {
         assert!(result.is_some());
         assert_eq!(result.unwrap().nominal_body_len, 77);
     }
+
+    #[test]
+    fn test_passwords_are_masked_when_displayed() {
+        let data = b"8=FIX.4.4\x019=51\x0135=A\x01553=trader\x01554=secret\x01925=new-secret\x0110=000\x01";
+        let message = RawFixMessage::new(data.to_vec());
+
+        let expected = "8=FIX.4.4|9=51|35=A|553=trader|554=***|925=***|10=000|";
+        assert_eq!(message.to_string(), expected);
+        assert!(!format!("{:?}", message).contains("secret"));
+    }
 }
@@ -0,0 +1,83 @@
+use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
+
+use crate::message::{FixMessage, MessageRejection};
+
+/// A session-level reject, sent by either side when a message can't be processed.
+#[derive(Clone, Debug)]
+pub struct Reject {
+    pub ref_seq_num: u64,
+    pub ref_tag_id: Option<u32>,
+    pub ref_msg_type: Option<String>,
+    pub session_reject_reason: Option<fix44::SessionRejectReason>,
+    pub text: Option<String>,
+}
+
+impl Reject {
+    pub fn new(ref_seq_num: u64) -> Self {
+        Self {
+            ref_seq_num,
+            ref_tag_id: None,
+            ref_msg_type: None,
+            session_reject_reason: None,
+            text: None,
+        }
+    }
+
+    pub fn with_ref_tag_id(mut self, ref_tag_id: u32) -> Self {
+        self.ref_tag_id = Some(ref_tag_id);
+        self
+    }
+
+    pub fn with_ref_msg_type(mut self, ref_msg_type: &str) -> Self {
+        self.ref_msg_type = Some(ref_msg_type.to_string());
+        self
+    }
+
+    pub fn with_session_reject_reason(mut self, reason: fix44::SessionRejectReason) -> Self {
+        self.session_reject_reason = Some(reason);
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+}
+
+impl FixMessage for Reject {
+    fn write(&self, msg: &mut Message) {
+        msg.set(fix44::REF_SEQ_NUM, self.ref_seq_num);
+        if let Some(ref_tag_id) = self.ref_tag_id {
+            msg.set(fix44::REF_TAG_ID, ref_tag_id);
+        }
+        if let Some(ref_msg_type) = &self.ref_msg_type {
+            msg.set(fix44::REF_MSG_TYPE, ref_msg_type.as_str());
+        }
+        if let Some(reason) = self.session_reject_reason {
+            msg.set(fix44::SESSION_REJECT_REASON, reason);
+        }
+        if let Some(text) = &self.text {
+            msg.set(fix44::TEXT, text.as_str());
+        }
+    }
+
+    fn message_type(&self) -> &str {
+        "3"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        Ok(Self {
+            ref_seq_num: message
+                .get(fix44::REF_SEQ_NUM)
+                .map_err(|_| MessageRejection::missing_field(fix44::REF_SEQ_NUM))?,
+            ref_tag_id: message.get(fix44::REF_TAG_ID).ok(),
+            ref_msg_type: message
+                .get::<&str>(fix44::REF_MSG_TYPE)
+                .ok()
+                .map(|t| t.to_string()),
+            session_reject_reason: message.get(fix44::SESSION_REJECT_REASON).ok(),
+            text: message.get::<&str>(fix44::TEXT).ok().map(|t| t.to_string()),
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use hotfix_message::{fix44, HardCodedFixFieldDefinition};
+
+/// Why a received message was refused, either while parsing it or by the application.
+///
+/// The session answers the counterparty based on the variant, filling in the
+/// sequence number and type of the refused message.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum MessageRejection {
+    /// Answered with a session-level Reject(3).
+    #[error("message rejected: {reason:?}")]
+    Reject {
+        reason: fix44::SessionRejectReason,
+        ref_tag_id: Option<u32>,
+        text: Option<String>,
+    },
+    /// Answered with a BusinessMessageReject(j).
+    #[error("message rejected by the application: {reason:?}")]
+    BusinessReject {
+        reason: fix44::BusinessRejectReason,
+        business_reject_ref_id: Option<String>,
+        text: Option<String>,
+    },
+    /// The session logs out with the given reason.
+    #[error("logging out: {text}")]
+    Logout { text: String },
+}
+
+impl MessageRejection {
+    pub fn reject(reason: fix44::SessionRejectReason) -> Self {
+        Self::Reject {
+            reason,
+            ref_tag_id: None,
+            text: None,
+        }
+    }
+
+    pub fn business_reject(reason: fix44::BusinessRejectReason) -> Self {
+        Self::BusinessReject {
+            reason,
+            business_reject_ref_id: None,
+            text: None,
+        }
+    }
+
+    pub fn logout(text: &str) -> Self {
+        Self::Logout {
+            text: text.to_string(),
+        }
+    }
+
+    /// Rejects a message that doesn't have a field we need.
+    pub fn missing_field(field: &HardCodedFixFieldDefinition) -> Self {
+        Self::reject(fix44::SessionRejectReason::RequiredTagMissing)
+            .with_ref_tag_id(field.tag)
+            .with_text(&format!("{}({}) is missing", field.name, field.tag))
+    }
+
+    /// Sets the tag that caused a session-level reject, it's ignored for other rejections.
+    pub fn with_ref_tag_id(mut self, tag: u32) -> Self {
+        if let Self::Reject { ref_tag_id, .. } = &mut self {
+            *ref_tag_id = Some(tag);
+        }
+        self
+    }
+
+    /// Sets the business-level ID of the refused message, such as its ClOrdID(11).
+    ///
+    /// It's ignored for anything but business rejects.
+    pub fn with_business_reject_ref_id(mut self, id: &str) -> Self {
+        if let Self::BusinessReject {
+            business_reject_ref_id,
+            ..
+        } = &mut self
+        {
+            *business_reject_ref_id = Some(id.to_string());
+        }
+        self
+    }
+
+    pub fn with_text(mut self, new_text: &str) -> Self {
+        match &mut self {
+            Self::Reject { text, .. } | Self::BusinessReject { text, .. } => {
+                *text = Some(new_text.to_string());
+            }
+            Self::Logout { text } => *text = new_text.to_string(),
+        }
+        self
+    }
+}
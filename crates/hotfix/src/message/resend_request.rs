@@ -0,0 +1,42 @@
+use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
+
+use crate::message::{FixMessage, MessageRejection};
+
+#[derive(Clone, Debug)]
+pub struct ResendRequest {
+    begin_seq_no: u64,
+    end_seq_no: u64,
+}
+
+impl ResendRequest {
+    /// Requests everything from `begin_seq_no` onwards, an `end_seq_no` of 0 means infinity.
+    pub fn new(begin_seq_no: u64, end_seq_no: u64) -> Self {
+        Self {
+            begin_seq_no,
+            end_seq_no,
+        }
+    }
+}
+
+impl FixMessage for ResendRequest {
+    fn write(&self, msg: &mut Message) {
+        msg.set(fix44::BEGIN_SEQ_NO, self.begin_seq_no);
+        msg.set(fix44::END_SEQ_NO, self.end_seq_no);
+    }
+
+    fn message_type(&self) -> &str {
+        "2"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        Ok(Self {
+            begin_seq_no: message
+                .get(fix44::BEGIN_SEQ_NO)
+                .map_err(|_| MessageRejection::missing_field(fix44::BEGIN_SEQ_NO))?,
+            end_seq_no: message
+                .get(fix44::END_SEQ_NO)
+                .map_err(|_| MessageRejection::missing_field(fix44::END_SEQ_NO))?,
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use crate::message::FixMessage;
+use crate::message::{FixMessage, MessageRejection};
 use hotfix_message::field_types::Timestamp;
 use hotfix_message::message::Message;
 use hotfix_message::{fix44, Part};
@@ -13,16 +13,24 @@ impl FixMessage for SequenceReset {
     fn write(&self, msg: &mut Message) {
         msg.set(fix44::GAP_FILL_FLAG, self.gap_fill);
         msg.set(fix44::NEW_SEQ_NO, self.new_seq_no);
-        let sending_time: Timestamp = msg.header().get(fix44::SENDING_TIME).unwrap();
-        msg.header_mut().set(fix44::ORIG_SENDING_TIME, sending_time);
-        msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+        if self.gap_fill {
+            // gap fills are only sent in place of resent messages
+            let sending_time: Timestamp = msg.header().get(fix44::SENDING_TIME).unwrap();
+            msg.header_mut().set(fix44::ORIG_SENDING_TIME, sending_time);
+            msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+        }
     }
 
     fn message_type(&self) -> &str {
         "4"
     }
 
-    fn parse(_message: &Message) -> Self {
-        todo!()
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        Ok(Self {
+            gap_fill: message.get(fix44::GAP_FILL_FLAG).unwrap_or(false),
+            new_seq_no: message
+                .get(fix44::NEW_SEQ_NO)
+                .map_err(|_| MessageRejection::missing_field(fix44::NEW_SEQ_NO))?,
+        })
     }
 }
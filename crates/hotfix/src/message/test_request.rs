@@ -0,0 +1,32 @@
+use hotfix_message::message::Message;
+use hotfix_message::{fix44, Part};
+
+use crate::message::{FixMessage, MessageRejection};
+
+#[derive(Clone, Debug)]
+pub struct TestRequest {
+    test_req_id: String,
+}
+
+impl TestRequest {
+    pub fn new(test_req_id: String) -> Self {
+        Self { test_req_id }
+    }
+}
+
+impl FixMessage for TestRequest {
+    fn write(&self, msg: &mut Message) {
+        msg.set(fix44::TEST_REQ_ID, self.test_req_id.as_str());
+    }
+
+    fn message_type(&self) -> &str {
+        "1"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        let test_req_id: &str = message
+            .get(fix44::TEST_REQ_ID)
+            .map_err(|_| MessageRejection::missing_field(fix44::TEST_REQ_ID))?;
+        Ok(Self::new(test_req_id.to_string()))
+    }
+}
@@ -1,268 +1,1238 @@
+mod delivery;
+mod dictionary;
+mod error;
+mod event;
 mod message;
+mod schedule;
 mod state;
+mod validation;
 
-use hotfix_message::dict::Dictionary;
+pub use delivery::{Delivery, DeliveryOutcome};
+pub use error::{ConfigError, DictionaryError, SessionError};
+pub use event::{SessionEvent, SessionEventKind};
+
+use chrono::{DateTime, Utc};
 use hotfix_message::field_types::Timestamp;
 use hotfix_message::message::{Config as MessageConfig, Message};
-use hotfix_message::{fix44, FieldType, Part};
+use hotfix_message::{fix44, fixt11, FieldType, Part};
+use std::collections::{BTreeMap, VecDeque};
 use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::select;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::time::{sleep, Duration, Instant, Sleep};
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
 use crate::actors::application::{ApplicationMessage, ApplicationRef};
 use crate::actors::socket_writer::WriterRef;
-use crate::config::SessionConfig;
-use crate::message::generate_message;
+use crate::config::{OutboundPolicy, SecretSource, SessionConfig};
+use crate::message::build_message;
+use crate::message::business_reject::BusinessReject;
 use crate::message::heartbeat::Heartbeat;
 use crate::message::logon::{Logon, ResetSeqNumConfig};
+use crate::message::logout::Logout;
 use crate::message::parser::RawFixMessage;
-use crate::message::FixMessage;
+use crate::message::reject::Reject;
+use crate::message::resend_request::ResendRequest;
+use crate::message::{FixMessage, MessageRejection};
 use crate::store::MessageStore;
 
 use crate::message::sequence_reset::SequenceReset;
+use crate::message::test_request::TestRequest;
 use crate::message_utils::is_admin;
+use dictionary::SessionDictionary;
 use message::SessionMessage;
 use state::SessionState;
+use validation::{
+    validate_header, validate_logon, validate_message, validate_orig_sending_time, HeaderError,
+};
+
+/// Which side of the connection the session is on.
+///
+/// Initiators open the connection and send the first logon,
+/// acceptors wait for the peer's logon and respond to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionType {
+    Initiator,
+    Acceptor,
+}
+
+const PASSWORD_FIELD: &str = "Password(554)";
+const NEW_PASSWORD_FIELD: &str = "NewPassword(925)";
+
+/// Reads a secret sent on logon.
+fn resolve_secret(field: &'static str, secret: &SecretSource) -> Result<String, ConfigError> {
+    secret
+        .resolve()
+        .map_err(|source| ConfigError::Secret { field, source })
+}
+
+/// How many events are kept for subscribers that haven't received them yet.
+const EVENT_CAPACITY: usize = 256;
 
 #[derive(Clone)]
 pub struct SessionRef<M> {
     sender: mpsc::Sender<SessionMessage<M>>,
+    posted: mpsc::UnboundedSender<M>,
+    events: broadcast::Sender<SessionEvent>,
 }
 
 impl<M: FixMessage> SessionRef<M> {
+    /// Starts the session task.
+    ///
+    /// Fails if the dictionaries of the config can't be loaded or its secrets can't be read.
     pub fn new(
         config: SessionConfig,
+        connection_type: ConnectionType,
         application: ApplicationRef<M>,
         store: impl MessageStore + Send + Sync + 'static,
-    ) -> Self {
+    ) -> Result<Self, ConfigError> {
         let (sender, mailbox) = mpsc::channel::<SessionMessage<M>>(10);
-        let actor = Session::new(mailbox, config, application, store);
+        let (posted, posted_mailbox) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let actor = Session::new(
+            mailbox,
+            posted_mailbox,
+            events.clone(),
+            config,
+            connection_type,
+            application,
+            store,
+        )?;
         tokio::spawn(run_session(actor));
 
-        Self { sender }
+        Ok(Self {
+            sender,
+            posted,
+            events,
+        })
     }
 
-    pub async fn register_writer(&self, writer: WriterRef) {
-        self.sender
-            .send(SessionMessage::Connected(writer))
-            .await
-            .expect("be able to register writer");
+    /// Subscribes to the session's events from now on.
+    ///
+    /// Subscribers that fall behind by more than a few hundred events miss the oldest ones,
+    /// see [broadcast::Receiver::recv].
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
     }
 
-    pub async fn new_fix_message_received(&self, msg: RawFixMessage) {
-        self.sender
-            .send(SessionMessage::FixMessageReceived(msg))
+    /// Hands a new connection to the session.
+    ///
+    /// Fails with [SessionError::AlreadyConnected] or [SessionError::OutsideSessionTime]
+    /// if the session refuses the connection, in which case it has been closed.
+    pub async fn register_writer(&self, writer: WriterRef) -> Result<(), SessionError> {
+        self.request(|responder| SessionMessage::Connected { writer, responder })
             .await
-            .expect("be able to receive message");
     }
 
-    pub async fn disconnect(&self, reason: String) {
-        self.sender
-            .send(SessionMessage::Disconnected(reason))
+    pub async fn new_fix_message_received(&self, msg: RawFixMessage) -> Result<(), SessionError> {
+        self.send(SessionMessage::FixMessageReceived(msg)).await
+    }
+
+    /// Tells the session the connection has been closed, it's ignored if it isn't the one in use.
+    pub async fn disconnect(&self, connection_id: u64, reason: String) -> Result<(), SessionError> {
+        self.send(SessionMessage::Disconnected {
+            connection_id,
+            reason,
+        })
+        .await
+    }
+
+    /// Sends an application message, returning once the session has decided what to do with it.
+    ///
+    /// This mustn't be awaited from within [Application](crate::Application) callbacks,
+    /// the session may be waiting for the callback to return, use [SessionRef::post_message] instead.
+    pub async fn send_message(&self, msg: M) -> Result<Delivery, SessionError> {
+        let (responder, receiver) = oneshot::channel();
+        self.send(SessionMessage::SendMessage {
+            message: msg,
+            responder,
+        })
+        .await?;
+        receiver.await.map_err(|_| SessionError::SessionStopped)
+    }
+
+    /// Hands an application message to the session without waiting for it to be handled.
+    ///
+    /// Unlike [SessionRef::send_message], this can be called from within
+    /// [Application](crate::Application) callbacks. Posted messages are sent in order
+    /// according to the [OutboundPolicy] of the session, and the
+    /// [Delivery] is discarded. Fails only if the session has stopped.
+    pub fn post_message(&self, msg: M) -> Result<(), SessionError> {
+        self.posted
+            .send(msg)
+            .map_err(|_| SessionError::SessionStopped)
+    }
+
+    pub async fn logout(&self, reason: String) -> Result<(), SessionError> {
+        let (responder, receiver) = oneshot::channel();
+        self.send(SessionMessage::Logout { reason, responder })
+            .await?;
+        // the session only drops the responder if it's shutting down, we're logged out either way
+        let _ = receiver.await;
+        Ok(())
+    }
+
+    pub async fn should_reconnect(&self) -> Result<bool, SessionError> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(SessionMessage::ShouldReconnect(sender)).await?;
+        receiver.await.map_err(|_| SessionError::SessionStopped)
+    }
+
+    /// Sets the sequence number of the next message we send.
+    pub async fn set_next_sender_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.request(|responder| SessionMessage::SetNextSenderSeqNum { seq_num, responder })
             .await
-            .expect("be able to send disconnect");
     }
 
-    pub async fn send_message(&self, msg: M) {
-        self.sender
-            .send(SessionMessage::SendMessage(msg))
+    /// Sets the sequence number we expect on the next message from the peer.
+    pub async fn set_next_target_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.request(|responder| SessionMessage::SetNextTargetSeqNum { seq_num, responder })
             .await
-            .expect("message to send successfully");
     }
 
-    pub async fn should_reconnect(&self) -> bool {
-        let (sender, receiver) = oneshot::channel();
+    /// Sends a SequenceReset(4) in reset mode, telling the peer to expect `new_seq_no` next.
+    ///
+    /// Our next sender sequence number moves forward to `new_seq_no` too.
+    pub async fn send_sequence_reset(&self, new_seq_no: u64) -> Result<(), SessionError> {
+        self.request(|responder| SessionMessage::SendSequenceReset {
+            new_seq_no,
+            responder,
+        })
+        .await
+    }
+
+    /// Asks the peer to resend the given range, an `end` of 0 means everything from `begin` onwards.
+    pub async fn send_resend_request(&self, begin: u64, end: u64) -> Result<(), SessionError> {
+        self.request(|responder| SessionMessage::SendResendRequest {
+            begin,
+            end,
+            responder,
+        })
+        .await
+    }
+
+    async fn request(
+        &self,
+        message: impl FnOnce(oneshot::Sender<Result<(), SessionError>>) -> SessionMessage<M>,
+    ) -> Result<(), SessionError> {
+        let (responder, receiver) = oneshot::channel();
+        self.send(message(responder)).await?;
+        receiver.await.map_err(|_| SessionError::SessionStopped)?
+    }
+
+    async fn send(&self, message: SessionMessage<M>) -> Result<(), SessionError> {
         self.sender
-            .send(SessionMessage::ShouldReconnect(sender))
+            .send(message)
             .await
-            .unwrap();
-        receiver.await.expect("to receive a response")
+            .map_err(|_| SessionError::SessionStopped)
     }
 }
 
 struct Session<M, S> {
     mailbox: mpsc::Receiver<SessionMessage<M>>,
+    /// Application messages sent without waiting for a response, see [SessionRef::post_message].
+    posted: mpsc::UnboundedReceiver<M>,
+    events: broadcast::Sender<SessionEvent>,
     message_config: MessageConfig,
     config: SessionConfig,
-    dictionary: Dictionary,
+    connection_type: ConnectionType,
+    dictionary: SessionDictionary,
     state: SessionState,
+    /// The connection we're using, kept after logging out until its reader reports it closed.
+    connection_id: Option<u64>,
     application: ApplicationRef<M>,
     store: S,
+    /// The interval in seconds we send heartbeats at, which the peer chooses if we're the acceptor.
+    heartbeat_interval: u64,
     heartbeat_timer: Pin<Box<Sleep>>,
+    /// Fires when we haven't received anything from the peer for longer than the heartbeat interval.
+    peer_timer: Pin<Box<Sleep>>,
+    /// Fires when the peer hasn't responded to our logout in time, only used while awaiting logout.
+    logout_timer: Pin<Box<Sleep>>,
+    logout_responder: Option<oneshot::Sender<()>>,
+    /// Fires when the session period starts or ends, only used if the session has a schedule.
+    schedule_timer: Pin<Box<Sleep>>,
+    test_request_outstanding: Option<String>,
+    /// The gap we're waiting for the peer to fill.
+    resend_in_progress: Option<InboundResend>,
+    /// Messages received ahead of a sequence gap, to be processed once the gap is filled.
+    queued_messages: BTreeMap<u64, RawFixMessage>,
+    /// Application messages waiting for us to log on, see [OutboundPolicy::Queue],
+    /// or for our resends to complete.
+    outbound_queue: VecDeque<(M, oneshot::Sender<()>)>,
+    /// Application messages persisted while we weren't logged on, see [OutboundPolicy::ResendAsPossDup].
+    outbound_pending_resend: Option<PendingResend>,
+    /// Ranges of our messages being resent, a chunk at a time in between other work.
+    outbound_resends: VecDeque<OutboundResend>,
+    /// Whether we have logged on with the NewPassword(925), which has become our password since.
+    password_changed: bool,
+}
+
+struct InboundResend {
+    /// The highest sequence number received since the gap was detected.
+    end: u64,
+    /// The end of the range we have asked for, 0 if we have asked for everything.
+    requested_end: u64,
+}
+
+struct OutboundResend {
+    /// The first sequence number of the next chunk.
+    next: u64,
+    end: u64,
+    /// Where the admin messages skipped so far start, to be gap filled before the next resent message.
+    reset_start: Option<u64>,
+}
+
+/// A range of persisted messages to be resent once we have logged on.
+struct PendingResend {
+    begin: u64,
+    end: u64,
+    /// The creation time of the store when the messages were persisted, to tell whether it has been reset since.
+    store_created: DateTime<Utc>,
 }
 
 impl<M: FixMessage, S: MessageStore> Session<M, S> {
     fn new(
         mailbox: mpsc::Receiver<SessionMessage<M>>,
+        posted: mpsc::UnboundedReceiver<M>,
+        events: broadcast::Sender<SessionEvent>,
         config: SessionConfig,
+        connection_type: ConnectionType,
         application: ApplicationRef<M>,
         store: S,
-    ) -> Session<M, S> {
-        let heartbeat_timer = sleep(Duration::from_secs(config.heartbeat_interval));
-        Self {
+    ) -> Result<Session<M, S>, ConfigError> {
+        let heartbeat_interval = config.heartbeat_interval;
+        let heartbeat_timer = sleep(Duration::from_secs(heartbeat_interval));
+        let peer_timer = sleep(peer_timeout(config.heartbeat_interval));
+        let logout_timer = sleep(Duration::from_secs(config.logout_timeout));
+        // check the schedule straight away, in case a new period has started while we were down
+        let schedule_timer = sleep(Duration::ZERO);
+        let dictionary = SessionDictionary::new(&config)?;
+        // secrets are read again on every logon, but a config that can't work is refused early
+        for (field, secret) in [
+            (PASSWORD_FIELD, &config.password),
+            (NEW_PASSWORD_FIELD, &config.new_password),
+        ] {
+            if let Some(secret) = secret {
+                resolve_secret(field, secret)?;
+            }
+        }
+        Ok(Self {
             mailbox,
+            posted,
+            events,
             config,
+            connection_type,
             message_config: MessageConfig::default(),
-            dictionary: Dictionary::fix44(),
+            dictionary,
             state: SessionState::Disconnected {
                 reconnect: true,
                 reason: "initialising".to_string(),
             },
+            connection_id: None,
             application,
             store,
+            heartbeat_interval,
             heartbeat_timer: Box::pin(heartbeat_timer),
-        }
+            peer_timer: Box::pin(peer_timer),
+            logout_timer: Box::pin(logout_timer),
+            logout_responder: None,
+            schedule_timer: Box::pin(schedule_timer),
+            test_request_outstanding: None,
+            resend_in_progress: None,
+            queued_messages: BTreeMap::new(),
+            outbound_queue: VecDeque::new(),
+            outbound_pending_resend: None,
+            outbound_resends: VecDeque::new(),
+            password_changed: false,
+        })
+    }
+
+    fn emit(&self, kind: SessionEventKind) {
+        // sending only fails if nobody is subscribed
+        let _ = self.events.send(SessionEvent::new(kind));
     }
 
     async fn on_incoming(&mut self, raw_message: RawFixMessage) {
         debug!("received message: {}", raw_message);
+        self.on_peer_activity();
+        self.process_message(raw_message, true).await;
+        self.process_queued_messages().await;
+        self.check_resend_completion().await;
+    }
+
+    /// Processes a message from the peer, `check_header` is only unset for queued messages,
+    /// whose header was checked when they arrived.
+    async fn process_message(&mut self, raw_message: RawFixMessage, check_header: bool) {
+        let message = match self.dictionary.parse(&self.message_config, &raw_message) {
+            Ok(message) => message,
+            Err(err) => {
+                // garbled messages are ignored, the sequence gap gets them resent
+                error!("ignoring message that can't be parsed: {err}");
+                return;
+            }
+        };
+        let Ok(message_type) = message.header().get::<&str>(fix44::MSG_TYPE) else {
+            self.logout_and_disconnect("MsgType(35) not found".to_string())
+                .await;
+            return;
+        };
+        let msg_seq_num: u64 = match message.header().get(fix44::MSG_SEQ_NUM) {
+            Ok(seq_num) => seq_num,
+            Err(_) => {
+                self.logout_and_disconnect("MsgSeqNum(34) not found".to_string())
+                    .await;
+                return;
+            }
+        };
+
+        if check_header {
+            if let Err(err) = validate_header(&message, &self.config, message_type, msg_seq_num) {
+                self.on_invalid_header(err, msg_seq_num).await;
+                return;
+            }
+        }
+
+        if message_type == "4" && !is_gap_fill(&message) {
+            // sequence resets in reset mode are processed regardless of their sequence number
+            self.on_sequence_reset(&message, msg_seq_num).await;
+            return;
+        }
+
+        if message_type == "A" && self.is_reset_requested_by_peer(&message) {
+            // an invalid logon mustn't wipe our messages before we log out,
+            // once reset, the next message we send is the first one
+            if let Err(reason) = validate_logon(&message, &self.config, false, 1) {
+                error!(reason, "invalid logon");
+                self.logout_and_disconnect(reason).await;
+                return;
+            }
+            self.store.reset().await;
+        }
+
+        let expected_seq_num = self.store.next_target_seq_number().await;
+        if msg_seq_num > expected_seq_num {
+            // logons, logouts and resend requests are still processed when the sequence number is too high
+            match message_type {
+                "2" => {
+                    self.on_resend_request(&message).await;
+                    self.on_sequence_gap(expected_seq_num, msg_seq_num).await;
+                }
+                "5" => self.on_logout(&message).await,
+                "A" => {
+                    self.on_logon(&message).await;
+                    self.on_sequence_gap(expected_seq_num, msg_seq_num).await;
+                }
+                _ => {
+                    self.queued_messages.insert(msg_seq_num, raw_message);
+                    self.on_sequence_gap(expected_seq_num, msg_seq_num).await;
+                    return;
+                }
+            }
+            self.application
+                .send_message(ApplicationMessage::ReceivedAdmin(message))
+                .await;
+            return;
+        } else if msg_seq_num < expected_seq_num {
+            let poss_dup: bool = message.header().get(fix44::POSS_DUP_FLAG).unwrap_or(false);
+            if poss_dup {
+                if let Err(reject) = validate_orig_sending_time(&message, message_type, msg_seq_num)
+                {
+                    warn!(msg_seq_num, "rejecting duplicate: {:?}", reject.text);
+                    self.send_message(reject).await;
+                    return;
+                }
+                debug!(
+                    msg_seq_num,
+                    "ignoring duplicate of an already processed message"
+                );
+            } else {
+                let reason = format!(
+                    "MsgSeqNum too low, expecting {expected_seq_num} but received {msg_seq_num}"
+                );
+                error!(reason);
+                self.logout_and_disconnect(reason).await;
+            }
+            return;
+        }
+
         self.store.increment_target_seq_number().await;
 
-        let message = Message::from_bytes(
-            &self.message_config,
-            &self.dictionary,
-            raw_message.as_bytes(),
-        );
-        let message_type = message.header().get(fix44::MSG_TYPE).unwrap();
+        let Some(application_dictionary) = self.dictionary.application(&message) else {
+            let reject = Reject::new(msg_seq_num)
+                .with_ref_msg_type(message_type)
+                .with_ref_tag_id(fixt11::APPL_VER_ID.tag)
+                .with_session_reject_reason(fix44::SessionRejectReason::ValueIsIncorrect)
+                .with_text("unsupported ApplVerID(1128)");
+            self.send_message(reject).await;
+            return;
+        };
+        if let Err(reject) = validate_message(
+            &message,
+            self.dictionary.transport(),
+            application_dictionary,
+            self.config.validation.as_ref(),
+            message_type,
+            msg_seq_num,
+        ) {
+            warn!(msg_seq_num, "rejecting invalid message: {:?}", reject.text);
+            self.send_message(reject).await;
+            return;
+        }
 
+        let admin = is_admin(message_type);
         match message_type {
             "0" => {
-                // TODO: handle heartbeat
+                self.on_heartbeat(&message);
             }
             "1" => {
-                // TODO: handle test request
+                self.on_test_request(&message).await;
             }
             "2" => {
                 self.on_resend_request(&message).await;
             }
             "3" => {
-                // TODO: handle reject
+                self.on_reject(&message, msg_seq_num).await;
             }
             "4" => {
-                // TODO: handle sequence reset
+                self.on_sequence_reset(&message, msg_seq_num).await;
             }
             "5" => {
-                self.on_logout().await;
+                self.on_logout(&message).await;
             }
             "A" => {
-                self.on_logon().await;
+                self.on_logon(&message).await;
+            }
+            _ if !M::is_supported(message_type) => {
+                let reject = BusinessReject::new(
+                    message_type,
+                    fix44::BusinessRejectReason::UnsupportedMessageType,
+                )
+                .with_ref_seq_num(msg_seq_num)
+                .with_text(&format!("unsupported MsgType(35)={message_type}"));
+                self.send_message(reject).await;
             }
             _ => {
-                let parsed_message = M::parse(&message);
-                let app_message = ApplicationMessage::ReceivedMessage(parsed_message);
-                self.application.send_message(app_message).await;
+                let possible_duplicate: bool =
+                    message.header().get(fix44::POSS_DUP_FLAG).unwrap_or(false);
+                let result = match M::parse(&message) {
+                    Ok(app_message) => match self
+                        .application
+                        .deliver_message(app_message, possible_duplicate)
+                        .await
+                    {
+                        Ok(result) => result,
+                        Err(err) => {
+                            error!("failed to deliver message: {err}");
+                            Err(MessageRejection::business_reject(
+                                fix44::BusinessRejectReason::ApplicationNotAvailable,
+                            ))
+                        }
+                    },
+                    Err(rejection) => Err(rejection),
+                };
+                if let Err(rejection) = result {
+                    self.on_message_rejected(rejection, message_type, msg_seq_num)
+                        .await;
+                }
+            }
+        }
+
+        if admin {
+            self.application
+                .send_message(ApplicationMessage::ReceivedAdmin(message))
+                .await;
+        }
+    }
+
+    async fn on_invalid_header(&mut self, err: HeaderError, msg_seq_num: u64) {
+        let reason = match err {
+            HeaderError::BeginString(reason) => reason,
+            HeaderError::Rejected(reject) => {
+                let reason = reject.text.clone().unwrap_or_default();
+                if msg_seq_num == self.store.next_target_seq_number().await {
+                    self.store.increment_target_seq_number().await;
+                }
+                self.send_message(reject).await;
+                reason
+            }
+        };
+        error!("logging out after invalid header: {reason}");
+        self.logout_and_disconnect(reason).await;
+    }
+
+    fn on_peer_activity(&mut self) {
+        // any message proves that the peer is still alive
+        self.test_request_outstanding = None;
+        self.reset_peer_timer();
+    }
+
+    fn on_heartbeat(&mut self, message: &Message) {
+        if let Ok(test_req_id) = message.get::<&str>(fix44::TEST_REQ_ID) {
+            debug!(
+                test_req_id,
+                "received heartbeat in response to test request"
+            );
+        }
+    }
+
+    async fn on_test_request(&mut self, message: &Message) {
+        let test_req_id: &str = message.get(fix44::TEST_REQ_ID).unwrap_or_default();
+        let heartbeat = Heartbeat::for_test_request(test_req_id.to_string());
+        self.send_message(heartbeat).await;
+    }
+
+    async fn on_peer_timeout(&mut self) {
+        self.reset_peer_timer();
+        if !matches!(self.state, SessionState::Active { .. }) {
+            return;
+        }
+
+        match &self.test_request_outstanding {
+            Some(test_req_id) => {
+                error!(
+                    test_req_id,
+                    "peer hasn't responded to our test request, disconnecting"
+                );
+                self.state.disconnect().await;
+            }
+            None => {
+                let millis = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                let test_req_id = format!("TEST-{millis}");
+                warn!(
+                    test_req_id,
+                    "haven't heard from the peer, sending test request"
+                );
+                self.test_request_outstanding = Some(test_req_id.clone());
+                self.send_message(TestRequest::new(test_req_id)).await;
+            }
+        }
+    }
+
+    /// Answers the peer when we, or the application, refused one of its messages.
+    async fn on_message_rejected(
+        &mut self,
+        rejection: MessageRejection,
+        message_type: &str,
+        msg_seq_num: u64,
+    ) {
+        warn!(msg_seq_num, message_type, "refusing message: {rejection}");
+        match rejection {
+            MessageRejection::Reject {
+                reason,
+                ref_tag_id,
+                text,
+            } => {
+                let mut reject = Reject::new(msg_seq_num)
+                    .with_ref_msg_type(message_type)
+                    .with_session_reject_reason(reason);
+                reject.ref_tag_id = ref_tag_id;
+                reject.text = text;
+                self.send_message(reject).await;
+            }
+            MessageRejection::BusinessReject {
+                reason,
+                business_reject_ref_id,
+                text,
+            } => {
+                let mut reject =
+                    BusinessReject::new(message_type, reason).with_ref_seq_num(msg_seq_num);
+                reject.business_reject_ref_id = business_reject_ref_id;
+                reject.text = text;
+                self.send_message(reject).await;
+            }
+            MessageRejection::Logout { text } => {
+                self.logout(text, true).await;
+            }
+        }
+    }
+
+    async fn on_reject(&mut self, message: &Message, msg_seq_num: u64) {
+        let reject = match Reject::parse(message) {
+            Ok(reject) => reject,
+            Err(rejection) => {
+                self.on_message_rejected(rejection, "3", msg_seq_num).await;
+                return;
+            }
+        };
+        warn!(
+            ref_seq_num = reject.ref_seq_num,
+            "peer rejected our message: {:?}", reject.text
+        );
+        self.emit(SessionEventKind::RejectReceived {
+            ref_seq_num: reject.ref_seq_num,
+            text: reject.text.clone(),
+        });
+
+        let seq_num = reject.ref_seq_num as usize;
+        let rejected_message = self
+            .store
+            .get_slice(seq_num, seq_num)
+            .await
+            .into_iter()
+            .next()
+            .and_then(|raw| {
+                let message = self
+                    .dictionary
+                    .parse(&self.message_config, &RawFixMessage::new(raw))
+                    .ok()?;
+                let message_type: &str = message.header().get(fix44::MSG_TYPE).ok()?;
+                if is_admin(message_type) {
+                    return None;
+                }
+                M::parse(&message).ok()
+            });
+
+        self.application
+            .send_message(ApplicationMessage::ReceivedReject {
+                reject,
+                rejected_message,
+            })
+            .await;
+    }
+
+    async fn on_sequence_reset(&mut self, message: &Message, msg_seq_num: u64) {
+        let new_seq_no: u64 = match message.get(fix44::NEW_SEQ_NO) {
+            Ok(new_seq_no) => new_seq_no,
+            Err(_) => {
+                let reject = Reject::new(msg_seq_num)
+                    .with_ref_msg_type("4")
+                    .with_ref_tag_id(fix44::NEW_SEQ_NO.tag)
+                    .with_session_reject_reason(fix44::SessionRejectReason::RequiredTagMissing)
+                    .with_text("NewSeqNo(36) is missing");
+                self.send_message(reject).await;
+                return;
+            }
+        };
+
+        // for gap fills, the expected number has already moved past the sequence reset itself
+        let expected_seq_num = self.store.next_target_seq_number().await;
+        if new_seq_no < expected_seq_num {
+            let text = format!(
+                "attempt to lower sequence number, invalid value NewSeqNo(36)={new_seq_no}"
+            );
+            error!(expected_seq_num, new_seq_no, "rejecting sequence reset");
+            let reject = Reject::new(msg_seq_num)
+                .with_ref_msg_type("4")
+                .with_ref_tag_id(fix44::NEW_SEQ_NO.tag)
+                .with_session_reject_reason(fix44::SessionRejectReason::ValueIsIncorrect)
+                .with_text(&text);
+            self.send_message(reject).await;
+            return;
+        }
+
+        if new_seq_no == expected_seq_num && !is_gap_fill(message) {
+            warn!(
+                new_seq_no,
+                "sequence reset doesn't change the sequence number"
+            );
+        }
+        debug!(new_seq_no, "applying sequence reset");
+        self.store.set_next_target_seq_number(new_seq_no).await;
+    }
+
+    async fn on_sequence_gap(&mut self, expected_seq_num: u64, msg_seq_num: u64) {
+        match &mut self.resend_in_progress {
+            Some(resend) => {
+                debug!(
+                    expected_seq_num,
+                    msg_seq_num, "sequence gap detected while already awaiting resend"
+                );
+                resend.end = resend.end.max(msg_seq_num);
+            }
+            None => {
+                warn!(
+                    expected_seq_num,
+                    msg_seq_num, "sequence gap detected, requesting resend"
+                );
+                let requested_end = self.resend_request_end(expected_seq_num, msg_seq_num);
+                self.resend_in_progress = Some(InboundResend {
+                    end: msg_seq_num,
+                    requested_end,
+                });
+                self.emit(SessionEventKind::GapDetected {
+                    expected: expected_seq_num,
+                    received: msg_seq_num,
+                });
+                self.send_message(ResendRequest::new(expected_seq_num, requested_end))
+                    .await;
+            }
+        }
+    }
+
+    /// Where the ResendRequest(2) for a gap starting at `begin` should end,
+    /// asking for at most [SessionConfig::resend_request_chunk_size] messages at once.
+    fn resend_request_end(&self, begin: u64, end: u64) -> u64 {
+        match self.config.resend_request_chunk_size {
+            0 => 0,
+            chunk_size => end.min(begin + chunk_size - 1),
+        }
+    }
+
+    async fn process_queued_messages(&mut self) {
+        loop {
+            let next_seq_num = self.store.next_target_seq_number().await;
+            // anything below the next sequence number has been gap filled in the meantime
+            self.queued_messages = self.queued_messages.split_off(&next_seq_num);
+            match self.queued_messages.remove(&next_seq_num) {
+                Some(raw_message) => {
+                    debug!(next_seq_num, "processing queued message");
+                    // SendingTime(52) would be checked against the time of release otherwise
+                    self.process_message(raw_message, false).await;
+                }
+                None => break,
+            }
+        }
+    }
+
+    async fn check_resend_completion(&mut self) {
+        let Some(resend) = &self.resend_in_progress else {
+            return;
+        };
+        let (end, requested_end) = (resend.end, resend.requested_end);
+        let next_seq_num = self.store.next_target_seq_number().await;
+        if next_seq_num > end {
+            debug!(end, "sequence gap has been filled");
+            self.resend_in_progress = None;
+            self.emit(SessionEventKind::ResendCompleted { end_seq_num: end });
+        } else if requested_end != 0 && next_seq_num > requested_end {
+            // the chunk we have asked for has been resent, ask for the next one
+            let requested_end = self.resend_request_end(next_seq_num, end);
+            debug!(
+                next_seq_num,
+                requested_end, "requesting next chunk of the gap"
+            );
+            if let Some(resend) = &mut self.resend_in_progress {
+                resend.requested_end = requested_end;
             }
+            self.send_message(ResendRequest::new(next_seq_num, requested_end))
+                .await;
         }
     }
 
-    async fn on_connect(&mut self, writer: WriterRef) {
+    fn is_reset_requested_by_peer(&self, message: &Message) -> bool {
+        // if we have sent our logon already, we're the ones who requested the reset,
+        // and a logon is only valid while we're waiting for one
+        let awaiting_peer_logon = matches!(
+            self.state,
+            SessionState::AwaitingLogon {
+                logon_sent: false,
+                ..
+            }
+        );
+        awaiting_peer_logon
+            && matches!(
+                message.get(fix44::RESET_SEQ_NUM_FLAG),
+                Ok(fix44::ResetSeqNumFlag::Yes)
+            )
+    }
+
+    async fn on_connect(&mut self, writer: WriterRef) -> Result<(), SessionError> {
+        if !matches!(self.state, SessionState::Disconnected { .. }) {
+            warn!("received a new connection while the session is already connected - dropping it");
+            writer.disconnect().await;
+            return Err(SessionError::AlreadyConnected);
+        }
+        if let Some(schedule) = &self.config.schedule {
+            if !schedule.is_in_session(Utc::now()) {
+                warn!("received a new connection outside of session time - dropping it");
+                writer.disconnect().await;
+                return Err(SessionError::OutsideSessionTime);
+            }
+        }
+        self.reset_if_new_period().await;
+
+        self.connection_id = Some(writer.connection_id());
+        // the peer's logon may choose another interval, for this connection only
+        self.heartbeat_interval = self.config.heartbeat_interval;
         self.state = SessionState::AwaitingLogon {
             writer,
             logon_sent: false,
         };
-        self.send_logon().await;
+        self.emit(SessionEventKind::Connected);
+        self.on_peer_activity();
+        if self.connection_type == ConnectionType::Initiator {
+            self.send_logon().await;
+        }
+        Ok(())
     }
 
-    async fn on_disconnect(&mut self, reason: String) {
-        match self.state {
-            SessionState::Active { .. } | SessionState::AwaitingLogon { .. } => {
-                self.state = SessionState::Disconnected {
-                    reconnect: true,
-                    reason,
-                }
+    async fn on_disconnect(&mut self, connection_id: u64, reason: String) {
+        if self.connection_id != Some(connection_id) {
+            debug!(
+                connection_id,
+                reason, "ignoring disconnect of a connection we don't use"
+            );
+            return;
+        }
+        self.connection_id = None;
+        self.resend_in_progress = None;
+        self.outbound_resends.clear();
+        self.queued_messages.clear();
+        if let Some(responder) = self.logout_responder.take() {
+            let _ = responder.send(());
+        }
+        let (reconnect, reason) = match self.state {
+            SessionState::AwaitingLogout { reconnect, .. } => (reconnect, reason),
+            SessionState::Active { .. } | SessionState::AwaitingLogon { .. } => (true, reason),
+            SessionState::LoggedOut { reconnect } => (reconnect, "logged out".to_string()),
+            SessionState::Disconnected { .. } => {
+                warn!("disconnect message was received, but the session is already disconnected");
+                return;
+            }
+        };
+        self.emit(SessionEventKind::Disconnected {
+            reason: reason.clone(),
+        });
+        self.state = SessionState::Disconnected { reconnect, reason };
+    }
+
+    async fn on_logon(&mut self, message: &Message) {
+        let SessionState::AwaitingLogon { writer, logon_sent } = &self.state else {
+            error!("received unexpected logon message");
+            return;
+        };
+        let writer = writer.clone();
+        let logon_sent = *logon_sent;
+
+        let next_sender_seq_num = self.store.next_sender_seq_number().await;
+        if let Err(reason) = validate_logon(message, &self.config, logon_sent, next_sender_seq_num)
+        {
+            error!(reason, "invalid logon");
+            self.logout_and_disconnect(reason).await;
+            return;
+        }
+        if let Ok(appl_ver_id) = message.get(fixt11::DEFAULT_APPL_VER_ID) {
+            if !self.dictionary.set_peer_default_appl_ver_id(appl_ver_id) {
+                let reason = format!("unsupported DefaultApplVerID(1137)={appl_ver_id:?}");
+                error!(reason, "invalid logon");
+                self.logout_and_disconnect(reason).await;
+                return;
+            }
+        }
+
+        if !logon_sent {
+            // we are the acceptor, so we need to respond to the peer's logon
+            // and use the heartbeat interval the peer has chosen
+            self.heartbeat_interval = message.get(fix44::HEART_BT_INT).unwrap_or_default();
+            let reset_requested = matches!(
+                message.get(fix44::RESET_SEQ_NUM_FLAG),
+                Ok(fix44::ResetSeqNumFlag::Yes)
+            );
+            if !self.send_logon_response(reset_requested).await {
+                return;
             }
-            SessionState::LoggedOut { reconnect } => {
-                self.state = SessionState::Disconnected {
-                    reconnect,
-                    reason: "logged out".to_string(),
+            self.reset_peer_timer();
+        }
+        self.state = SessionState::Active { writer };
+        self.password_changed |= self.config.new_password.is_some();
+        self.emit(SessionEventKind::LoggedOn);
+
+        // the peer tells us which of our messages it has missed, these are resent straight away
+        if let Ok(next_expected) = message.get::<u64>(fix44::NEXT_EXPECTED_MSG_SEQ_NUM) {
+            if next_expected < next_sender_seq_num {
+                debug!(
+                    next_expected,
+                    "peer has missed some of our messages, resending them"
+                );
+                self.resend_messages(next_expected as usize, next_sender_seq_num as usize - 1)
+                    .await;
+                if self
+                    .outbound_pending_resend
+                    .as_ref()
+                    .is_some_and(|pending| pending.begin >= next_expected)
+                {
+                    self.outbound_pending_resend = None;
                 }
             }
-            SessionState::Disconnected { .. } => {
-                warn!("disconnect message was received, but the session is already disconnected")
+        }
+
+        self.flush_outbound().await;
+    }
+
+    async fn send_app_message(&mut self, message: M) -> Delivery {
+        let (flushed, flushed_receiver) = oneshot::channel();
+        if let SessionState::Active { .. } = self.state {
+            if !self.outbound_resends.is_empty() {
+                // new messages must not be interleaved with the ones being resent
+                debug!("resending messages, queueing message until we're done");
+                self.outbound_queue.push_back((message, flushed));
+                return Delivery::new(DeliveryOutcome::Queued, flushed_receiver);
             }
+            let seq_num = self.send_to_peer(message, flushed).await;
+            return Delivery::new(DeliveryOutcome::Written { seq_num }, flushed_receiver);
         }
+
+        let outcome = match self.config.outbound_policy {
+            OutboundPolicy::Queue => {
+                debug!("not logged on, queueing message until we are");
+                self.outbound_queue.push_back((message, flushed));
+                DeliveryOutcome::Queued
+            }
+            OutboundPolicy::Reject => {
+                warn!("not logged on, dropping message");
+                DeliveryOutcome::Rejected
+            }
+            OutboundPolicy::ResendAsPossDup => {
+                let (seq_num, _) = self.store_message(message).await;
+                debug!(seq_num, "not logged on, message will be resent once we are");
+                match &mut self.outbound_pending_resend {
+                    Some(pending) => pending.end = seq_num,
+                    None => {
+                        self.outbound_pending_resend = Some(PendingResend {
+                            begin: seq_num,
+                            end: seq_num,
+                            store_created: self.store.creation_time().await,
+                        })
+                    }
+                }
+                DeliveryOutcome::Persisted { seq_num }
+            }
+        };
+        Delivery::new(outcome, flushed_receiver)
     }
 
-    async fn on_logon(&mut self) {
-        // TODO: this should check if logon message has the right sequence numbers
-        // TODO: this should wait to see if a resend request is sent
-        if let SessionState::AwaitingLogon { writer, .. } = &self.state {
-            self.state = SessionState::Active {
-                writer: writer.clone(),
+    /// Sends the application messages held back while we weren't logged on.
+    async fn flush_outbound(&mut self) {
+        if let Some(pending) = self.outbound_pending_resend.take() {
+            if self.store.creation_time().await == pending.store_created {
+                self.resend_messages(pending.begin as usize, pending.end as usize)
+                    .await;
+            } else {
+                error!(
+                    begin = pending.begin,
+                    end = pending.end,
+                    "sequence numbers have been reset, messages sent while logged out are lost"
+                );
             }
-        } else {
-            error!("received unexpected logon message");
         }
+
+        self.send_queued().await;
     }
 
-    async fn on_logout(&mut self) {
-        // TODO: reconnect = false isn't always valid, this should be more sophisticated
-        self.state.disconnect().await;
-        self.state = SessionState::LoggedOut { reconnect: false };
+    /// Sends the queued application messages, unless we're still resending messages.
+    async fn send_queued(&mut self) {
+        while self.outbound_resends.is_empty() {
+            let Some((message, flushed)) = self.outbound_queue.pop_front() else {
+                break;
+            };
+            self.send_to_peer(message, flushed).await;
+        }
+    }
+
+    /// Sends an application message, letting the application know it's being sent.
+    async fn send_to_peer(&mut self, message: M, flushed: oneshot::Sender<()>) -> u64 {
         self.application
-            .send_logout("peer has logged us out".to_string())
+            .send_message(ApplicationMessage::SendingMessage(message.clone()))
             .await;
+        let msg_type = message.message_type().as_bytes().to_vec();
+        let (seq_num, msg) = self.store_message(message).await;
+        self.send_raw(&msg_type, msg, Some(flushed)).await;
+        seq_num
     }
 
-    async fn on_resend_request(&mut self, message: &Message) {
-        // TODO: verify message and send reject as necessary
+    async fn logout_and_disconnect(&mut self, reason: String) {
+        self.send_message(Logout::with_reason(reason)).await;
+        self.state.disconnect().await;
+        self.state = SessionState::LoggedOut { reconnect: true };
+    }
 
-        let begin_seq_number: usize = match message.get(fix44::BEGIN_SEQ_NO) {
-            Ok(seq_number) => seq_number,
-            Err(_) => {
-                // send reject if there is no valid begin number
-                todo!()
+    async fn logout(&mut self, reason: String, reconnect: bool) {
+        match &self.state {
+            SessionState::Active { writer } => {
+                let writer = writer.clone();
+                self.send_message(Logout::with_reason(reason)).await;
+                self.state = SessionState::AwaitingLogout { writer, reconnect };
+                let deadline = Instant::now() + Duration::from_secs(self.config.logout_timeout);
+                self.logout_timer.as_mut().reset(deadline);
+            }
+            SessionState::AwaitingLogon { .. } => {
+                // there's nobody to log out from yet
+                self.state.disconnect().await;
+                self.state = SessionState::LoggedOut { reconnect };
             }
+            SessionState::AwaitingLogout { .. } => {
+                warn!("logout is already in progress");
+            }
+            SessionState::LoggedOut { .. } | SessionState::Disconnected { .. } => {
+                self.state = SessionState::Disconnected { reconnect, reason };
+            }
+        }
+    }
+
+    async fn on_schedule_check(&mut self) {
+        let Some(schedule) = &self.config.schedule else {
+            return;
         };
+        let now = Utc::now();
+        let in_session = schedule.is_in_session(now);
+        let period_start = schedule.period_start(now);
+        let next_transition = schedule.next_transition(now);
 
-        let end_seq_number: usize = match message.get(fix44::END_SEQ_NO) {
-            Ok(seq_number) => {
-                let last_seq_number = self.store.next_sender_seq_number().await as usize - 1;
-                if seq_number == 0 {
-                    last_seq_number
-                } else {
-                    std::cmp::min(seq_number, last_seq_number)
-                }
+        let connected = !matches!(
+            self.state,
+            SessionState::Disconnected { .. } | SessionState::LoggedOut { .. }
+        );
+        let new_period = self.store.creation_time().await < period_start;
+        if connected && !in_session {
+            info!("session time has ended, logging out");
+            self.logout("end of session".to_string(), true).await;
+        } else if connected && new_period {
+            // the sequence numbers are reset once we have reconnected
+            info!("new session period has started, logging out");
+            self.logout("end of session".to_string(), true).await;
+        } else if !connected && in_session {
+            self.reset_if_new_period().await;
+        }
+
+        let wait = (next_transition - now).to_std().unwrap_or_default();
+        self.schedule_timer.as_mut().reset(Instant::now() + wait);
+    }
+
+    /// Resets the store if it was created before the start of the current session period.
+    async fn reset_if_new_period(&mut self) {
+        let Some(schedule) = &self.config.schedule else {
+            return;
+        };
+        let period_start = schedule.period_start(Utc::now());
+        if self.store.creation_time().await < period_start {
+            info!(%period_start, "new session period, resetting sequence numbers");
+            self.store.reset().await;
+        }
+    }
+
+    async fn on_logout(&mut self, message: &Message) {
+        let text: &str = message.get(fix44::TEXT).unwrap_or_default();
+        self.emit(SessionEventKind::LogoutReceived {
+            reason: text.to_string(),
+        });
+        let reason = match self.state {
+            SessionState::AwaitingLogout { reconnect, .. } => {
+                debug!("peer has acknowledged our logout");
+                self.state.disconnect().await;
+                self.state = SessionState::LoggedOut { reconnect };
+                "logged out".to_string()
             }
-            Err(_) => {
-                // send reject if there is no valid end number
-                todo!()
+            _ => {
+                let text: &str = message.get(fix44::TEXT).unwrap_or("peer has logged us out");
+                let reason = text.to_string();
+                warn!(reason, "peer has logged us out");
+                self.send_message(Logout::default()).await;
+                self.state.disconnect().await;
+                self.state = SessionState::LoggedOut { reconnect: true };
+                reason
             }
         };
+        self.application.send_logout(reason).await;
+    }
+
+    async fn on_logout_timeout(&mut self) {
+        if let SessionState::AwaitingLogout { reconnect, .. } = self.state {
+            warn!("peer hasn't responded to our logout, disconnecting");
+            self.state.disconnect().await;
+            self.state = SessionState::LoggedOut { reconnect };
+        }
+    }
+
+    async fn on_resend_request(&mut self, message: &Message) {
+        // both fields have been validated by the time we get here
+        let (Ok(begin_seq_number), Ok(end_seq_number)) = (
+            message.get::<usize>(fix44::BEGIN_SEQ_NO),
+            message.get::<usize>(fix44::END_SEQ_NO),
+        ) else {
+            return;
+        };
 
-        self.resend_messages(begin_seq_number, end_seq_number, message)
-            .await;
+        let last_seq_number = self.store.next_sender_seq_number().await as usize - 1;
+        let end_seq_number = if end_seq_number == 0 {
+            last_seq_number
+        } else {
+            std::cmp::min(end_seq_number, last_seq_number)
+        };
+
+        self.resend_messages(begin_seq_number, end_seq_number).await;
     }
 
-    async fn resend_messages(&mut self, begin: usize, end: usize, _message: &Message) {
+    /// Resends our messages from `begin` to `end`.
+    ///
+    /// The first chunk is resent straight away, the rest in between other work
+    /// so that heartbeats aren't held up by long resends.
+    async fn resend_messages(&mut self, begin: usize, end: usize) {
+        if begin > end {
+            warn!(begin, end, "ignoring resend of messages we haven't sent");
+            return;
+        }
         debug!(begin, end, "resending messages as requested");
-        let messages = self.store.get_slice(begin, end).await;
+        self.outbound_resends.push_back(OutboundResend {
+            next: begin as u64,
+            end: end as u64,
+            reset_start: None,
+        });
+        if self.outbound_resends.len() == 1 {
+            self.resend_next_chunk().await;
+        }
+    }
 
-        let no = messages.len();
-        debug!(no, "number of messages");
+    async fn resend_next_chunk(&mut self) {
+        let Some(resend) = self.outbound_resends.pop_front() else {
+            return;
+        };
+        let OutboundResend {
+            next: begin,
+            end,
+            mut reset_start,
+        } = resend;
+        let chunk_end = end.min(begin + self.config.resend_chunk_size.max(1) as u64 - 1);
+        let messages = self
+            .store
+            .get_slice(begin as usize, chunk_end as usize)
+            .await;
 
-        let mut reset_start: Option<u64> = None;
-        let mut sequence_number = 0;
+        let no = messages.len();
+        debug!(begin, chunk_end, no, "resending chunk of messages");
 
+        let mut expected_seq_num = begin;
         for msg in messages {
-            let m = String::from_utf8(msg.clone()).unwrap();
-            debug!(m, "resending message");
-            let mut message =
-                Message::from_bytes(&self.message_config, &self.dictionary, msg.as_slice());
-            sequence_number = message.header().get(fix44::MSG_SEQ_NUM).unwrap();
+            debug!("resending message: {}", RawFixMessage::new(msg.clone()));
+            // without a sequence number, a stored message is gap filled like a missing one
+            let Ok(mut message) = self
+                .dictionary
+                .parse(&self.message_config, &RawFixMessage::new(msg))
+            else {
+                error!("skipping stored message that can't be parsed");
+                continue;
+            };
+            let Ok(sequence_number) = message.header().get::<u64>(fix44::MSG_SEQ_NUM) else {
+                error!("skipping stored message without a valid MsgSeqNum(34)");
+                continue;
+            };
             let message_type: String = message
                 .header()
                 .get::<&str>(fix44::MSG_TYPE)
-                .unwrap()
+                .unwrap_or_default()
                 .to_string();
 
+            if sequence_number > expected_seq_num && reset_start.is_none() {
+                // messages missing from the store are gap filled too
+                reset_start = Some(expected_seq_num);
+            }
+            expected_seq_num = sequence_number + 1;
+
             if is_admin(message_type.as_str()) {
                 debug!("skipping message as it's an admin message");
-                if reset_start.is_none() {
-                    reset_start = Some(sequence_number);
-                }
+                reset_start.get_or_insert(sequence_number);
+                continue;
+            }
+            if message_type.is_empty() || Self::prepare_message_for_resend(&mut message).is_none() {
+                error!(
+                    sequence_number,
+                    "gap filling stored message that can't be resent"
+                );
+                reset_start.get_or_insert(sequence_number);
                 continue;
             }
 
@@ -272,54 +1242,109 @@ impl<M: FixMessage, S: MessageStore> Session<M, S> {
                 reset_start = None;
             }
 
-            Self::prepare_message_for_resend(&mut message);
             self.send_raw(
                 message_type.as_bytes(),
                 message.encode(&self.message_config),
+                None,
             )
             .await;
             debug!(sequence_number, "resent message");
         }
 
-        if let Some(begin) = reset_start {
-            // the final reset if needed
-            let end = sequence_number;
-            self.send_sequence_reset(begin, end).await;
+        if expected_seq_num <= chunk_end && reset_start.is_none() {
+            reset_start = Some(expected_seq_num);
+        }
+
+        if chunk_end < end {
+            self.outbound_resends.push_front(OutboundResend {
+                next: chunk_end + 1,
+                end,
+                reset_start,
+            });
+        } else if let Some(begin) = reset_start {
+            // the final reset if needed, the peer should expect the message after the range next
+            self.send_sequence_reset(begin, end + 1).await;
+        }
+
+        if self.outbound_resends.is_empty() && matches!(self.state, SessionState::Active { .. }) {
+            self.send_queued().await;
         }
     }
 
-    fn prepare_message_for_resend(msg: &mut Message) {
+    /// Flags the message as a possible duplicate, returns `None` if it has no valid SendingTime(52).
+    fn prepare_message_for_resend(msg: &mut Message) -> Option<()> {
         let header = msg.header_mut();
-        let raw_sending_time = header.pop(fix44::SENDING_TIME).unwrap();
-        let original_sending_time = Timestamp::deserialize(&raw_sending_time.data).unwrap();
+        let raw_sending_time = header.get_raw(fix44::SENDING_TIME)?;
+        let original_sending_time = Timestamp::deserialize(raw_sending_time).ok()?;
         header.set(fix44::ORIG_SENDING_TIME, original_sending_time);
         header.set(fix44::SENDING_TIME, Timestamp::utc_now());
         header.set(fix44::POSS_DUP_FLAG, true);
+        Some(())
     }
 
     fn reset_timer(&mut self) {
-        let deadline = Instant::now() + Duration::from_secs(self.config.heartbeat_interval);
+        let deadline = Instant::now() + Duration::from_secs(self.heartbeat_interval);
         self.heartbeat_timer.as_mut().reset(deadline);
     }
 
+    fn reset_peer_timer(&mut self) {
+        let deadline = Instant::now() + peer_timeout(self.heartbeat_interval);
+        self.peer_timer.as_mut().reset(deadline);
+    }
+
     async fn send_message(&mut self, message: impl FixMessage) {
+        let msg_type = message.message_type().as_bytes().to_vec();
+        let (_, msg) = self.store_message(message).await;
+        self.send_raw(&msg_type, msg, None).await;
+    }
+
+    /// Assigns the next sequence number to the message and persists it without sending.
+    async fn store_message(&mut self, message: impl FixMessage) -> (u64, Vec<u8>) {
         let seq_num = self.store.next_sender_seq_number().await;
         self.store.increment_sender_seq_number().await;
 
-        let msg_type = message.message_type().as_bytes().to_vec();
-        let msg = generate_message(
+        let msg = self.build_message(seq_num, message).await;
+        self.store.add(seq_num, &msg).await;
+        (seq_num, msg)
+    }
+
+    /// Encodes the message, giving the application a chance to amend admin messages first.
+    async fn build_message(&mut self, seq_num: u64, message: impl FixMessage) -> Vec<u8> {
+        let admin = is_admin(message.message_type());
+        let mut msg = build_message(
+            &self.config.begin_string,
             &self.config.sender_comp_id,
             &self.config.target_comp_id,
             seq_num as usize,
-            message,
+            message.clone(),
         );
-        self.store.add(seq_num, &msg).await;
-        self.send_raw(&msg_type, msg).await;
+        if admin {
+            msg = match self.application.prepare_admin_message(msg).await {
+                Ok(msg) => msg,
+                Err(err) => {
+                    // the amended message is lost with the application, so we send it as it is
+                    error!("failed to prepare admin message: {err}");
+                    build_message(
+                        &self.config.begin_string,
+                        &self.config.sender_comp_id,
+                        &self.config.target_comp_id,
+                        seq_num as usize,
+                        message,
+                    )
+                }
+            };
+        }
+        msg.encode(&self.message_config)
     }
 
-    async fn send_raw(&mut self, message_type: &[u8], data: Vec<u8>) {
+    async fn send_raw(
+        &mut self,
+        message_type: &[u8],
+        data: Vec<u8>,
+        flushed: Option<oneshot::Sender<()>>,
+    ) {
         self.state
-            .send_message(message_type, RawFixMessage::new(data))
+            .send_message(message_type, RawFixMessage::new(data), flushed)
             .await;
         self.reset_timer();
     }
@@ -329,27 +1354,164 @@ impl<M: FixMessage, S: MessageStore> Session<M, S> {
             gap_fill: true,
             new_seq_no: end,
         };
-        let raw_message = generate_message(
-            &self.config.sender_comp_id,
-            &self.config.target_comp_id,
-            begin as usize,
-            sequence_reset,
-        );
+        let raw_message = self.build_message(begin, sequence_reset).await;
 
-        self.send_raw(b"4", raw_message).await;
+        self.send_raw(b"4", raw_message, None).await;
         debug!(begin, end, "sent reset sequence");
     }
 
     async fn send_logon(&mut self) {
         let reset_config = if self.config.reset_on_logon {
+            ResetSeqNumConfig::Reset
+        } else {
+            ResetSeqNumConfig::NoReset(self.next_expected_msg_seq_num().await)
+        };
+        let logon = match self.new_logon(reset_config) {
+            Ok(logon) => logon,
+            Err(err) => {
+                self.disconnect_without_logon(err).await;
+                return;
+            }
+        };
+        if self.config.reset_on_logon {
             self.store.reset().await;
+        }
+
+        self.send_message(logon).await;
+        self.emit(SessionEventKind::LogonSent);
+    }
+
+    /// Responds to the peer's logon, returning `false` if we have disconnected instead.
+    async fn send_logon_response(&mut self, reset_requested: bool) -> bool {
+        // the store has already been reset when the peer's logon was received
+        let reset_config = if reset_requested {
             ResetSeqNumConfig::Reset
         } else {
-            ResetSeqNumConfig::NoReset(Some(self.store.next_target_seq_number().await))
+            ResetSeqNumConfig::NoReset(self.next_expected_msg_seq_num().await)
+        };
+        let logon = match self.new_logon(reset_config) {
+            Ok(logon) => logon,
+            Err(err) => {
+                self.disconnect_without_logon(err).await;
+                return false;
+            }
         };
-        let logon = Logon::new(self.config.heartbeat_interval, reset_config);
 
         self.send_message(logon).await;
+        self.emit(SessionEventKind::LogonSent);
+        true
+    }
+
+    /// Drops the connection when our logon can't be built, rather than logging on without credentials.
+    async fn disconnect_without_logon(&mut self, err: ConfigError) {
+        error!("not sending logon: {err}");
+        self.state.disconnect().await;
+        self.state = SessionState::LoggedOut { reconnect: true };
+    }
+
+    /// The NextExpectedMsgSeqNum(789) to send on logon, unless the FIX version doesn't have it.
+    async fn next_expected_msg_seq_num(&mut self) -> Option<u64> {
+        if !self.dictionary.has_field(fix44::NEXT_EXPECTED_MSG_SEQ_NUM) {
+            return None;
+        }
+        Some(self.store.next_target_seq_number().await)
+    }
+
+    fn new_logon(&self, reset_config: ResetSeqNumConfig) -> Result<Logon, ConfigError> {
+        let mut logon = Logon::new(self.heartbeat_interval, reset_config);
+        if let Some(appl_ver_id) = self.dictionary.default_appl_ver_id() {
+            logon = logon.with_default_appl_ver_id(appl_ver_id);
+        }
+        self.with_credentials(logon)
+    }
+
+    /// Adds the configured credentials to the logon.
+    ///
+    /// Secrets are read on every logon, so they can be rotated without a restart.
+    /// The NewPassword(925) is only sent until a logon with it succeeds, it's sent
+    /// as the Password(554) from then on.
+    fn with_credentials(&self, mut logon: Logon) -> Result<Logon, ConfigError> {
+        // sessions with credentials the dictionary doesn't define can't be created,
+        // this only keeps admin messages in line with the FIX version regardless
+        let has_field = |field| self.dictionary.has_field(field);
+        if let Some(username) = self
+            .config
+            .username
+            .as_ref()
+            .filter(|_| has_field(fix44::USERNAME))
+        {
+            logon = logon.with_username(username);
+        }
+        let (password, new_password) = match &self.config.new_password {
+            Some(new_password) if self.password_changed => (Some(new_password), None),
+            new_password => (self.config.password.as_ref(), new_password.as_ref()),
+        };
+        if let Some(password) = password.filter(|_| has_field(fix44::PASSWORD)) {
+            logon = logon.with_password(&resolve_secret(PASSWORD_FIELD, password)?);
+        }
+        if let Some(new_password) = new_password.filter(|_| has_field(fix44::NEW_PASSWORD)) {
+            logon = logon.with_new_password(&resolve_secret(NEW_PASSWORD_FIELD, new_password)?);
+        }
+        Ok(logon)
+    }
+
+    async fn set_next_sender_seq_num(&mut self, seq_num: u64) -> Result<(), SessionError> {
+        if seq_num == 0 {
+            return Err(SessionError::InvalidSequenceNumber(seq_num));
+        }
+        info!(seq_num, "setting next sender sequence number");
+        self.store.set_next_sender_seq_number(seq_num).await;
+        Ok(())
+    }
+
+    async fn set_next_target_seq_num(&mut self, seq_num: u64) -> Result<(), SessionError> {
+        if seq_num == 0 {
+            return Err(SessionError::InvalidSequenceNumber(seq_num));
+        }
+        info!(seq_num, "setting next target sequence number");
+        self.store.set_next_target_seq_number(seq_num).await;
+        Ok(())
+    }
+
+    async fn send_sequence_reset_in_reset_mode(
+        &mut self,
+        new_seq_no: u64,
+    ) -> Result<(), SessionError> {
+        if !matches!(self.state, SessionState::Active { .. }) {
+            return Err(SessionError::NotLoggedOn);
+        }
+        // the reset itself uses up the next sequence number, so we can only move forward past it
+        let seq_num = self.store.next_sender_seq_number().await;
+        if new_seq_no <= seq_num {
+            return Err(SessionError::InvalidSequenceNumber(new_seq_no));
+        }
+
+        info!(new_seq_no, "sending sequence reset");
+        let sequence_reset = SequenceReset {
+            gap_fill: false,
+            new_seq_no,
+        };
+        let raw_message = self.build_message(seq_num, sequence_reset).await;
+        self.store.add(seq_num, &raw_message).await;
+        self.store.set_next_sender_seq_number(new_seq_no).await;
+        self.send_raw(b"4", raw_message, None).await;
+        Ok(())
+    }
+
+    async fn send_resend_request(&mut self, begin: u64, end: u64) -> Result<(), SessionError> {
+        if !matches!(self.state, SessionState::Active { .. }) {
+            return Err(SessionError::NotLoggedOn);
+        }
+        if begin == 0 {
+            return Err(SessionError::InvalidSequenceNumber(begin));
+        }
+        if end != 0 && end < begin {
+            return Err(SessionError::InvalidSequenceNumber(end));
+        }
+
+        info!(begin, end, "requesting resend");
+        self.send_message(ResendRequest::new(begin, end)).await;
+        Ok(())
     }
 
     async fn handle(&mut self, message: SessionMessage<M>) {
@@ -358,27 +1520,87 @@ impl<M: FixMessage, S: MessageStore> Session<M, S> {
                 self.on_incoming(fix_message).await;
             }
             SessionMessage::SendHeartbeat => {
-                self.send_message(Heartbeat {}).await;
+                if let SessionState::Active { .. } = self.state {
+                    self.send_message(Heartbeat::default()).await;
+                } else {
+                    self.reset_timer();
+                }
+            }
+            SessionMessage::PeerTimeout => {
+                self.on_peer_timeout().await;
+            }
+            SessionMessage::SendMessage { message, responder } => {
+                let delivery = self.send_app_message(message).await;
+                let _ = responder.send(delivery);
             }
-            SessionMessage::SendMessage(message) => {
-                self.send_message(message).await;
+            SessionMessage::PostMessage(message) => {
+                self.send_app_message(message).await;
             }
-            SessionMessage::Disconnected(reason) => {
+            SessionMessage::Disconnected {
+                connection_id,
+                reason,
+            } => {
                 warn!(reason, "disconnected from peer");
-                self.on_disconnect(reason).await;
+                self.on_disconnect(connection_id, reason).await;
+            }
+            SessionMessage::Connected { writer, responder } => {
+                let _ = responder.send(self.on_connect(writer).await);
+            }
+            SessionMessage::Logout { reason, responder } => {
+                self.logout(reason, false).await;
+                if let SessionState::Disconnected { .. } = self.state {
+                    let _ = responder.send(());
+                } else {
+                    self.logout_responder = Some(responder);
+                }
+            }
+            SessionMessage::ScheduleCheck => {
+                self.on_schedule_check().await;
+            }
+            SessionMessage::ContinueResend => {
+                self.resend_next_chunk().await;
+            }
+            SessionMessage::LogoutTimeout => {
+                self.on_logout_timeout().await;
             }
-            SessionMessage::Connected(w) => {
-                self.on_connect(w).await;
+            SessionMessage::SetNextSenderSeqNum { seq_num, responder } => {
+                let _ = responder.send(self.set_next_sender_seq_num(seq_num).await);
+            }
+            SessionMessage::SetNextTargetSeqNum { seq_num, responder } => {
+                let _ = responder.send(self.set_next_target_seq_num(seq_num).await);
+            }
+            SessionMessage::SendSequenceReset {
+                new_seq_no,
+                responder,
+            } => {
+                let _ = responder.send(self.send_sequence_reset_in_reset_mode(new_seq_no).await);
+            }
+            SessionMessage::SendResendRequest {
+                begin,
+                end,
+                responder,
+            } => {
+                let _ = responder.send(self.send_resend_request(begin, end).await);
             }
             SessionMessage::ShouldReconnect(responder) => {
-                responder
-                    .send(self.state.should_reconnect())
-                    .expect("be able to respond");
+                let _ = responder.send(self.state.should_reconnect());
             }
         }
     }
 }
 
+fn is_gap_fill(message: &Message) -> bool {
+    message.get(fix44::GAP_FILL_FLAG).unwrap_or(false)
+}
+
+/// How long the peer can stay silent before we send a test request.
+///
+/// The peer should send a heartbeat every `heartbeat_interval` seconds,
+/// the additional 20% allows for transmission delays.
+fn peer_timeout(heartbeat_interval: u64) -> Duration {
+    Duration::from_millis(heartbeat_interval * 1200)
+}
+
 async fn run_session<M, S>(mut actor: Session<M, S>)
 where
     M: FixMessage,
@@ -396,11 +1618,1857 @@ where
                     None => break,
                 }
             }
+            Some(message) = actor.posted.recv() => {
+                actor.handle(SessionMessage::PostMessage(message)).await
+            }
             () = &mut actor.heartbeat_timer.as_mut() => {
                 actor.handle(SessionMessage::SendHeartbeat).await
             }
+            () = &mut actor.peer_timer.as_mut() => {
+                actor.handle(SessionMessage::PeerTimeout).await
+            }
+            () = &mut actor.logout_timer.as_mut(), if actor.state.is_awaiting_logout() => {
+                actor.handle(SessionMessage::LogoutTimeout).await
+            }
+            () = &mut actor.schedule_timer.as_mut(), if actor.config.schedule.is_some() => {
+                actor.handle(SessionMessage::ScheduleCheck).await
+            }
+            // select picks randomly among ready branches, so resends are interleaved with inbound
+            // traffic, new application messages are queued until the resends are done
+            () = std::future::ready(()), if !actor.outbound_resends.is_empty() => {
+                actor.handle(SessionMessage::ContinueResend).await
+            }
         }
     }
 
     debug!("session is shutting down")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use chrono_tz::Tz;
+    use hotfix_message::dict::{FieldLocation, FixDatatype};
+    use hotfix_message::validation::ValidationSettings;
+    use hotfix_message::HardCodedFixFieldDefinition;
+    use tokio::io::{duplex, AsyncReadExt};
+    use tokio::sync::broadcast;
+    use tokio::time::timeout;
+
+    use crate::actors::application::ApplicationRef;
+    use crate::config::{OutboundPolicy, ScheduleConfig, SecretSource, SessionConfig};
+    use crate::message::heartbeat::Heartbeat;
+    use crate::message::parser::{Parser, RawFixMessage};
+    use crate::message::{
+        build_message, fix44, fixt11, Config, Message, MessageRejection, Part, RepeatingGroup,
+        Timestamp,
+    };
+    use crate::session::{
+        ConfigError, ConnectionType, DeliveryOutcome, SessionError, SessionEvent, SessionEventKind,
+        SessionRef,
+    };
+    use crate::store::in_memory::InMemoryMessageStore;
+    use crate::store::MessageStore;
+    use crate::test_utils::{
+        logged_on_session, message_type, session_config, Counterparty, News, RecordingApplication,
+        PEER_COMP_ID, SESSION_COMP_ID,
+    };
+    use crate::transport::FixConnection;
+
+    #[tokio::test]
+    async fn test_sequence_gap_triggers_resend_request() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("B", 5, |msg| msg.set(fix44::HEADLINE, "skipped ahead"))
+            .await;
+
+        let resend_request = counterparty.receive().await;
+        assert_eq!(message_type(&resend_request), "2");
+        let begin: u64 = resend_request.get(fix44::BEGIN_SEQ_NO).unwrap();
+        let end: u64 = resend_request.get(fix44::END_SEQ_NO).unwrap();
+        assert_eq!(begin, 2);
+        assert_eq!(end, 0);
+    }
+
+    #[tokio::test]
+    async fn test_gap_is_requested_in_chunks() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.resend_request_chunk_size = 2;
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("B", 6, |msg| msg.set(fix44::HEADLINE, "sixth"))
+            .await;
+        for (expected_begin, expected_end) in [(2, 3), (4, 5)] {
+            let resend_request = counterparty.receive().await;
+            assert_eq!(message_type(&resend_request), "2");
+            let begin: u64 = resend_request.get(fix44::BEGIN_SEQ_NO).unwrap();
+            let end: u64 = resend_request.get(fix44::END_SEQ_NO).unwrap();
+            assert_eq!((begin, end), (expected_begin, expected_end));
+
+            for seq_num in begin..=end {
+                counterparty
+                    .send_with_seq_num("B", seq_num, |msg| {
+                        msg.set(fix44::HEADLINE, format!("resent {seq_num}").as_str())
+                    })
+                    .await;
+            }
+        }
+
+        for expected in ["resent 2", "resent 3", "resent 4", "resent 5", "sixth"] {
+            assert_eq!(received.recv().await, Some(News::new(expected)));
+        }
+        assert!(counterparty
+            .try_receive(Duration::from_millis(200))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_messages_after_gap_are_delivered_in_order() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("B", 4, |msg| msg.set(fix44::HEADLINE, "fourth"))
+            .await;
+        counterparty
+            .send_with_seq_num("B", 5, |msg| msg.set(fix44::HEADLINE, "fifth"))
+            .await;
+        let resend_request = counterparty.receive().await;
+        assert_eq!(message_type(&resend_request), "2");
+
+        counterparty
+            .send_with_seq_num("B", 2, |msg| msg.set(fix44::HEADLINE, "second"))
+            .await;
+        counterparty
+            .send_with_seq_num("B", 3, |msg| msg.set(fix44::HEADLINE, "third"))
+            .await;
+
+        for expected in ["second", "third", "fourth", "fifth"] {
+            assert_eq!(received.recv().await, Some(News::new(expected)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_messages_outlasting_max_latency_are_delivered() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.max_latency = 1;
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("B", 3, |msg| msg.set(fix44::HEADLINE, "third"))
+            .await;
+        let resend_request = counterparty.receive().await;
+        assert_eq!(message_type(&resend_request), "2");
+
+        // SendingTime(52) is checked against the wall clock, which tokio's paused clock doesn't move
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        counterparty
+            .send_with_seq_num("B", 2, |msg| msg.set(fix44::HEADLINE, "second"))
+            .await;
+
+        for expected in ["second", "third"] {
+            let message = timeout(Duration::from_secs(1), received.recv()).await;
+            assert_eq!(message.unwrap(), Some(News::new(expected)));
+        }
+        // neither a Reject nor a Logout
+        assert!(counterparty
+            .try_receive(Duration::from_millis(200))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_advances_expected_sequence_number() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("B", 4, |msg| msg.set(fix44::HEADLINE, "fourth"))
+            .await;
+        let resend_request = counterparty.receive().await;
+        assert_eq!(message_type(&resend_request), "2");
+
+        counterparty
+            .send_with_seq_num("4", 2, |msg| {
+                msg.set(fix44::GAP_FILL_FLAG, true);
+                msg.set(fix44::NEW_SEQ_NO, 4u64);
+                msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+            })
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("fourth")));
+
+        counterparty.next_seq_num = 5;
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "fifth"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("fifth")));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_reset_lowering_sequence_number_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send_with_seq_num("4", 1, |msg| {
+                msg.set(fix44::GAP_FILL_FLAG, false);
+                msg.set(fix44::NEW_SEQ_NO, 1u64);
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, 36);
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::ValueIsIncorrect);
+    }
+
+    #[tokio::test]
+    async fn test_test_request_is_answered_with_heartbeat() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("1", |msg| msg.set(fix44::TEST_REQ_ID, "are-you-there"))
+            .await;
+
+        let heartbeat = counterparty.receive().await;
+        assert_eq!(message_type(&heartbeat), "0");
+        let test_req_id: &str = heartbeat.get(fix44::TEST_REQ_ID).unwrap();
+        assert_eq!(test_req_id, "are-you-there");
+    }
+
+    #[tokio::test]
+    async fn test_silent_peer_is_sent_test_request_then_disconnected() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.heartbeat_interval = 1;
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        let mut events = session.subscribe();
+
+        let test_request = loop {
+            let message = counterparty.receive().await;
+            if message_type(&message) == "1" {
+                break message;
+            }
+        };
+        assert!(test_request.get::<&str>(fix44::TEST_REQ_ID).is_ok());
+
+        // we stay silent, so the session should drop the connection
+        let received = counterparty
+            .wait_for_disconnect(Duration::from_secs(5))
+            .await
+            .expect("session to close the connection");
+        assert!(received.iter().all(|message_type| message_type == "0"));
+        // the logon may still be in the queue of events
+        while !matches!(
+            next_event(&mut events).await,
+            SessionEventKind::Disconnected { .. }
+        ) {}
+    }
+
+    #[tokio::test]
+    async fn test_sequence_number_too_low_logs_out() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "in sequence"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("in sequence")));
+
+        counterparty
+            .send_with_seq_num("B", 2, |msg| msg.set(fix44::HEADLINE, "replayed"))
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let text: &str = logout.get(fix44::TEXT).unwrap();
+        assert_eq!(text, "MsgSeqNum too low, expecting 3 but received 2");
+    }
+
+    #[tokio::test]
+    async fn test_application_can_reply_from_within_callbacks() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (application, mut received) = RecordingApplication::new();
+        let (application, replies) = application.with_replies();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        replies.set(session.clone()).ok().unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+
+        for headline in ["first", "second"] {
+            counterparty
+                .send("B", |msg| msg.set(fix44::HEADLINE, headline))
+                .await;
+            assert_eq!(received.recv().await.unwrap().headline, headline);
+
+            // the session would be stuck waiting for the callback if the reply deadlocked
+            let reply = counterparty.receive().await;
+            assert_eq!(message_type(&reply), "B");
+            let reply_headline: &str = reply.get(fix44::HEADLINE).unwrap();
+            assert_eq!(reply_headline, format!("re: {headline}"));
+        }
+
+        let delivery = timeout(
+            Duration::from_secs(1),
+            session.send_message(News::new("after")),
+        )
+        .await
+        .expect("session to keep handling requests")
+        .unwrap();
+        assert!(matches!(delivery.outcome, DeliveryOutcome::Written { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_message_without_msg_type_logs_out() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.header_mut().pop(fix44::MSG_TYPE);
+            })
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let text: &str = logout.get(fix44::TEXT).unwrap();
+        assert_eq!(text, "MsgType(35) not found");
+        assert!(session.should_reconnect().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_missing_required_field_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty.send("1", |_| {}).await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_seq_num: u64 = reject.get(fix44::REF_SEQ_NUM).unwrap();
+        assert_eq!(ref_seq_num, 2);
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fix44::TEST_REQ_ID.tag);
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::RequiredTagMissing);
+    }
+
+    #[tokio::test]
+    async fn test_undefined_tag_is_rejected() {
+        const UNDEFINED: HardCodedFixFieldDefinition = HardCodedFixFieldDefinition {
+            name: "Undefined",
+            tag: 9999,
+            data_type: FixDatatype::String,
+            location: FieldLocation::Body,
+        };
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "with an unknown tag");
+                msg.set(&UNDEFINED, "value");
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, 9999);
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::InvalidTagNumber);
+
+        // the rejected message still consumes its sequence number
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "valid"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("valid")));
+    }
+
+    #[tokio::test]
+    async fn test_custom_fields_from_data_dictionary_are_accepted() {
+        const VENUE_TAG: HardCodedFixFieldDefinition = HardCodedFixFieldDefinition {
+            name: "VenueTag",
+            tag: 9999,
+            data_type: FixDatatype::String,
+            location: FieldLocation::Body,
+        };
+        let spec = include_str!("../../hotfix-dictionary/src/resources/quickfix/FIX-4.4.xml")
+            .replace(
+                "<fields>",
+                "<fields>\n  <field number='9999' name='VenueTag' type='STRING' />",
+            )
+            .replace(
+                "<message name='News' msgtype='B' msgcat='app'>",
+                "<message name='News' msgtype='B' msgcat='app'>\n   <field name='VenueTag' required='N' />",
+            );
+        let path = std::env::temp_dir().join("hotfix-test-custom-fix44.xml");
+        std::fs::write(&path, spec).unwrap();
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.data_dictionary_path = Some(path);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "with a custom tag");
+                msg.set(&VENUE_TAG, "value");
+            })
+            .await;
+
+        assert_eq!(received.recv().await, Some(News::new("with a custom tag")));
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_validated_against_the_dictionary() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.validation = Some(ValidationSettings::default());
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+
+        // news needs at least one line of text
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "without text"))
+            .await;
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::RequiredTagMissing);
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fix44::NO_LINES_OF_TEXT.tag);
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "with text");
+                let mut line = RepeatingGroup::new(fix44::NO_LINES_OF_TEXT, fix44::TEXT);
+                line.set(fix44::TEXT, "some text");
+                msg.set(fix44::NO_LINES_OF_TEXT, 1u32);
+                msg.set_groups(vec![line]);
+            })
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("with text")));
+    }
+
+    #[tokio::test]
+    async fn test_inbound_reject_is_passed_to_application() {
+        let (application, _received) = RecordingApplication::new();
+        let (application, mut rejects) = application.with_rejects();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+
+        // make sure the logon has been processed before sending
+        counterparty.sync().await;
+
+        session
+            .send_message(News::new("to be rejected"))
+            .await
+            .unwrap();
+        let news = counterparty.receive().await;
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+
+        counterparty
+            .send("3", |msg| {
+                msg.set(fix44::REF_SEQ_NUM, seq_num);
+                msg.set(fix44::TEXT, "not interested");
+            })
+            .await;
+
+        let (reject, rejected_message) = rejects.recv().await.unwrap();
+        assert_eq!(reject.ref_seq_num, seq_num);
+        assert_eq!(reject.text.as_deref(), Some("not interested"));
+        assert_eq!(rejected_message, Some(News::new("to be rejected")));
+    }
+
+    async fn next_event(events: &mut broadcast::Receiver<SessionEvent>) -> SessionEventKind {
+        tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .expect("an event to be published")
+            .unwrap()
+            .kind
+    }
+
+    #[tokio::test]
+    async fn test_session_events_are_published() {
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut events = session.subscribe();
+
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+        assert_eq!(next_event(&mut events).await, SessionEventKind::Connected);
+        assert_eq!(next_event(&mut events).await, SessionEventKind::LogonSent);
+        assert_eq!(next_event(&mut events).await, SessionEventKind::LoggedOn);
+
+        counterparty
+            .send_with_seq_num("B", 4, |msg| msg.set(fix44::HEADLINE, "ahead"))
+            .await;
+        assert_eq!(
+            next_event(&mut events).await,
+            SessionEventKind::GapDetected {
+                expected: 2,
+                received: 4
+            }
+        );
+
+        counterparty
+            .send_with_seq_num("5", 5, |msg| msg.set(fix44::TEXT, "closing"))
+            .await;
+        assert_eq!(
+            next_event(&mut events).await,
+            SessionEventKind::LogoutReceived {
+                reason: "closing".to_string()
+            }
+        );
+        assert!(matches!(
+            next_event(&mut events).await,
+            SessionEventKind::Disconnected { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_logon_includes_credentials() {
+        std::env::set_var("HOTFIX_TEST_LOGON_PASSWORD", "current-password");
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.username = Some("trader".to_string());
+        config.password = Some(SecretSource::Env {
+            env: "HOTFIX_TEST_LOGON_PASSWORD".to_string(),
+        });
+        config.new_password = Some(SecretSource::Plain("new-password".to_string()));
+        let (_session, mut counterparty) = connected_session(config).await;
+
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let username: &str = logon.get(fix44::USERNAME).unwrap();
+        assert_eq!(username, "trader");
+        let password: &str = logon.get(fix44::PASSWORD).unwrap();
+        assert_eq!(password, "current-password");
+        let new_password: &str = logon.get(fix44::NEW_PASSWORD).unwrap();
+        assert_eq!(new_password, "new-password");
+    }
+
+    #[tokio::test]
+    async fn test_new_password_is_the_password_after_logging_on_with_it() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.password = Some(SecretSource::Plain("current-password".to_string()));
+        config.new_password = Some(SecretSource::Plain("new-password".to_string()));
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        let mut events = session.subscribe();
+
+        drop(counterparty);
+        while !matches!(
+            next_event(&mut events).await,
+            SessionEventKind::Disconnected { .. }
+        ) {}
+        counterparty = Counterparty::connect(session.clone()).await;
+
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let password: &str = logon.get(fix44::PASSWORD).unwrap();
+        assert_eq!(password, "new-password");
+        assert!(logon.get::<&str>(fix44::NEW_PASSWORD).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_password_is_refused() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.password = Some(SecretSource::Env {
+            env: "HOTFIX_TEST_UNSET_PASSWORD".to_string(),
+        });
+        let (application, _received) = RecordingApplication::new();
+
+        let result = SessionRef::<News>::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::Secret {
+                field: "Password(554)",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_logon_isnt_sent_without_password() {
+        let path = std::env::temp_dir().join(format!("hotfix-password-{}", std::process::id()));
+        std::fs::write(&path, "current-password").unwrap();
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.password = Some(SecretSource::File { file: path.clone() });
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+
+        // the secret can't be read by the time we log on
+        std::fs::remove_file(&path).unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        let received = counterparty
+            .wait_for_disconnect(Duration::from_secs(5))
+            .await
+            .expect("session to close the connection");
+        assert!(received.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fixt_session_uses_application_version() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.begin_string = "FIXT.1.1".to_string();
+        let (application, mut received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone())
+            .await
+            .with_begin_string("FIXT.1.1");
+
+        let logon = counterparty.receive().await;
+        let begin_string: &str = logon.header().get(fix44::BEGIN_STRING).unwrap();
+        assert_eq!(begin_string, "FIXT.1.1");
+        let appl_ver_id: fixt11::ApplVerId = logon.get(fixt11::DEFAULT_APPL_VER_ID).unwrap();
+        assert_eq!(appl_ver_id, fixt11::ApplVerId::Fix50sp2);
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+                msg.set(fixt11::DEFAULT_APPL_VER_ID, fixt11::ApplVerId::Fix50sp2);
+            })
+            .await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "default version"))
+            .await;
+        assert_eq!(received.recv().await.unwrap(), News::new("default version"));
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fixt11::APPL_VER_ID, fixt11::ApplVerId::Fix44);
+                msg.set(fix44::HEADLINE, "overridden version");
+            })
+            .await;
+        assert_eq!(
+            received.recv().await.unwrap(),
+            News::new("overridden version")
+        );
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fixt11::APPL_VER_ID, fixt11::ApplVerId::Fix40);
+                msg.set(fix44::HEADLINE, "unsupported version");
+            })
+            .await;
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fixt11::APPL_VER_ID.tag);
+
+        session.send_message(News::new("outbound")).await.unwrap();
+        let news = counterparty.receive().await;
+        let begin_string: &str = news.header().get(fix44::BEGIN_STRING).unwrap();
+        assert_eq!(begin_string, "FIXT.1.1");
+        let headline: &str = news.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "outbound");
+    }
+
+    #[tokio::test]
+    async fn test_fix42_session_uses_fix42_messages() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.begin_string = "FIX.4.2".to_string();
+        config.reset_on_logon = false;
+        let (application, mut received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone())
+            .await
+            .with_begin_string("FIX.4.2");
+
+        let logon = counterparty.receive().await;
+        let begin_string: &str = logon.header().get(fix44::BEGIN_STRING).unwrap();
+        assert_eq!(begin_string, "FIX.4.2");
+        assert!(logon.get_raw(fix44::NEXT_EXPECTED_MSG_SEQ_NUM).is_none());
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+            })
+            .await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "inbound"))
+            .await;
+        assert_eq!(received.recv().await.unwrap(), News::new("inbound"));
+
+        session.send_message(News::new("outbound")).await.unwrap();
+        let news = counterparty.receive().await;
+        let begin_string: &str = news.header().get(fix44::BEGIN_STRING).unwrap();
+        assert_eq!(begin_string, "FIX.4.2");
+        let headline: &str = news.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "outbound");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_logon_requesting_reset_doesnt_reset_store() {
+        let mut store = InMemoryMessageStore::default();
+        store.set_next_sender_seq_number(4).await;
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Acceptor,
+            ApplicationRef::new(application),
+            store,
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 0u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+            })
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let seq_num: u64 = logout.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 4);
+    }
+
+    #[tokio::test]
+    async fn test_logon_with_different_heartbeat_interval_logs_out() {
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        assert_eq!(message_type(&counterparty.receive().await), "A");
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 60u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+            })
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let text: &str = logout.get(fix44::TEXT).unwrap();
+        assert_eq!(
+            text,
+            "HeartBtInt(108) doesn't match, expecting 30 but received 60"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_second_connection_is_refused_without_affecting_the_session() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        session.send_message(News::new("first")).await.unwrap();
+        counterparty.receive().await;
+
+        // someone else logs on with the same CompIDs, asking for a sequence reset
+        let mut logon = Message::new("FIX.4.4", "A");
+        logon.set(fix44::SENDER_COMP_ID, PEER_COMP_ID);
+        logon.set(fix44::TARGET_COMP_ID, SESSION_COMP_ID);
+        logon.set(fix44::MSG_SEQ_NUM, 1u64);
+        logon.set(fix44::SENDING_TIME, Timestamp::utc_now());
+        logon.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+        logon.set(fix44::HEART_BT_INT, 30u64);
+        logon.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+        let received = vec![RawFixMessage::new(logon.encode(&Config::default()))];
+        let (intruder_stream, mut intruder) = duplex(1024);
+        let result = FixConnection::accept(
+            intruder_stream,
+            session.clone(),
+            Parser::default(),
+            received,
+        )
+        .await;
+        assert_eq!(result.err(), Some(SessionError::AlreadyConnected));
+        let mut buf = vec![];
+        let read = timeout(Duration::from_secs(5), intruder.read_buf(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, 0);
+
+        // connection IDs start at 1, so this can't be the connection in use
+        session
+            .disconnect(0, "stale connection closed".to_string())
+            .await
+            .unwrap();
+
+        let delivery = session.send_message(News::new("second")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Written { seq_num: 3 });
+        let news = counterparty.receive().await;
+        let headline: &str = news.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "second");
+    }
+
+    #[tokio::test]
+    async fn test_logon_while_logged_on_doesnt_reset_sequence_numbers() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        session.send_message(News::new("first")).await.unwrap();
+        counterparty.receive().await;
+
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+            })
+            .await;
+        counterparty.sync().await;
+
+        // the heartbeat answering the sync has taken 3
+        let delivery = session.send_message(News::new("second")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Written { seq_num: 4 });
+    }
+
+    #[tokio::test]
+    async fn test_logon_from_unexpected_comp_id_logs_out() {
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, "SOMEONE-ELSE", 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        counterparty.logon().await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_resends_messages_missed_by_peer() {
+        // the session has sent two messages and a heartbeat before the previous connection dropped
+        let mut store = InMemoryMessageStore::default();
+        let sent = [
+            build_message(
+                "FIX.4.4",
+                SESSION_COMP_ID,
+                PEER_COMP_ID,
+                1,
+                News::new("first"),
+            )
+            .encode(&Config::default()),
+            build_message(
+                "FIX.4.4",
+                SESSION_COMP_ID,
+                PEER_COMP_ID,
+                2,
+                News::new("second"),
+            )
+            .encode(&Config::default()),
+            build_message(
+                "FIX.4.4",
+                SESSION_COMP_ID,
+                PEER_COMP_ID,
+                3,
+                Heartbeat::default(),
+            )
+            .encode(&Config::default()),
+        ];
+        for (seq_num, message) in (1..).zip(sent) {
+            store.add(seq_num, &message).await;
+            store.increment_sender_seq_number().await;
+        }
+
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.reset_on_logon = false;
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Acceptor,
+            ApplicationRef::new(application),
+            store,
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 45u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::No);
+                msg.set(fix44::NEXT_EXPECTED_MSG_SEQ_NUM, 2u64);
+            })
+            .await;
+
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let heartbeat_interval: u64 = logon.get(fix44::HEART_BT_INT).unwrap();
+        assert_eq!(heartbeat_interval, 45);
+        let next_expected: u64 = logon.get(fix44::NEXT_EXPECTED_MSG_SEQ_NUM).unwrap();
+        assert_eq!(next_expected, 2);
+
+        let resent = counterparty.receive().await;
+        assert_eq!(message_type(&resent), "B");
+        let seq_num: u64 = resent.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 2);
+        let poss_dup: bool = resent.header().get(fix44::POSS_DUP_FLAG).unwrap();
+        assert!(poss_dup);
+
+        let gap_fill = counterparty.receive().await;
+        assert_eq!(message_type(&gap_fill), "4");
+        let seq_num: u64 = gap_fill.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 3);
+        let new_seq_no: u64 = gap_fill.get(fix44::NEW_SEQ_NO).unwrap();
+        assert_eq!(new_seq_no, 4);
+    }
+
+    #[tokio::test]
+    async fn test_resend_request_beyond_last_message_is_ignored() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("2", |msg| {
+                msg.set(fix44::BEGIN_SEQ_NO, 10u64);
+                msg.set(fix44::END_SEQ_NO, 0u64);
+            })
+            .await;
+
+        counterparty.sync().await;
+        assert_eq!(session.should_reconnect().await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_messages_missing_from_store_are_gap_filled() {
+        // the second message has been lost from the store
+        let mut store = InMemoryMessageStore::default();
+        for (seq_num, headline) in [(1, "first"), (3, "third")] {
+            let mut message = build_message(
+                "FIX.4.4",
+                SESSION_COMP_ID,
+                PEER_COMP_ID,
+                seq_num,
+                News::new(headline),
+            );
+            store
+                .add(seq_num as u64, &message.encode(&Config::default()))
+                .await;
+        }
+        store.set_next_sender_seq_number(4).await;
+
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.reset_on_logon = false;
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Acceptor,
+            ApplicationRef::new(application),
+            store,
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::No);
+                msg.set(fix44::NEXT_EXPECTED_MSG_SEQ_NUM, 1u64);
+            })
+            .await;
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+
+        let first = counterparty.receive().await;
+        let headline: &str = first.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "first");
+        let gap_fill = counterparty.receive().await;
+        assert_eq!(message_type(&gap_fill), "4");
+        let seq_num: u64 = gap_fill.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 2);
+        let new_seq_no: u64 = gap_fill.get(fix44::NEW_SEQ_NO).unwrap();
+        assert_eq!(new_seq_no, 3);
+        let third = counterparty.receive().await;
+        let headline: &str = third.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "third");
+    }
+
+    #[tokio::test]
+    async fn test_stored_messages_that_cant_be_resent_are_gap_filled() {
+        let mut store = InMemoryMessageStore::default();
+        let mut first = build_message(
+            "FIX.4.4",
+            SESSION_COMP_ID,
+            PEER_COMP_ID,
+            1,
+            News::new("first"),
+        );
+        store.add(1, &first.encode(&Config::default())).await;
+        store.add(2, b"garbled").await;
+        let mut without_sending_time = build_message(
+            "FIX.4.4",
+            SESSION_COMP_ID,
+            PEER_COMP_ID,
+            3,
+            News::new("third"),
+        );
+        without_sending_time.header_mut().pop(fix44::SENDING_TIME);
+        store
+            .add(3, &without_sending_time.encode(&Config::default()))
+            .await;
+        let mut fourth = build_message(
+            "FIX.4.4",
+            SESSION_COMP_ID,
+            PEER_COMP_ID,
+            4,
+            News::new("fourth"),
+        );
+        store.add(4, &fourth.encode(&Config::default())).await;
+        store.set_next_sender_seq_number(5).await;
+
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.reset_on_logon = false;
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Acceptor,
+            ApplicationRef::new(application),
+            store,
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::No);
+                msg.set(fix44::NEXT_EXPECTED_MSG_SEQ_NUM, 1u64);
+            })
+            .await;
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+
+        let resent = counterparty.receive().await;
+        let headline: &str = resent.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "first");
+        let gap_fill = counterparty.receive().await;
+        assert_eq!(message_type(&gap_fill), "4");
+        let seq_num: u64 = gap_fill.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 2);
+        let new_seq_no: u64 = gap_fill.get(fix44::NEW_SEQ_NO).unwrap();
+        assert_eq!(new_seq_no, 4);
+        let resent = counterparty.receive().await;
+        let headline: &str = resent.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "fourth");
+        assert_eq!(session.should_reconnect().await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_long_resend_is_sent_in_chunks() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.resend_chunk_size = 2;
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        for i in 2..=6 {
+            session
+                .send_message(News::new(&format!("news {i}")))
+                .await
+                .unwrap();
+            counterparty.receive().await;
+        }
+
+        counterparty
+            .send("2", |msg| {
+                msg.set(fix44::BEGIN_SEQ_NO, 1u64);
+                msg.set(fix44::END_SEQ_NO, 0u64);
+            })
+            .await;
+
+        let gap_fill = counterparty.receive().await;
+        assert_eq!(message_type(&gap_fill), "4");
+        let new_seq_no: u64 = gap_fill.get(fix44::NEW_SEQ_NO).unwrap();
+        assert_eq!(new_seq_no, 2);
+        for i in 2..=6 {
+            let resent = counterparty.receive().await;
+            assert_eq!(message_type(&resent), "B");
+            let seq_num: u64 = resent.header().get(fix44::MSG_SEQ_NUM).unwrap();
+            assert_eq!(seq_num, i);
+            let headline: &str = resent.get(fix44::HEADLINE).unwrap();
+            assert_eq!(headline, format!("news {i}"));
+            let poss_dup: bool = resent.header().get(fix44::POSS_DUP_FLAG).unwrap();
+            assert!(poss_dup);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_sent_during_resend_follow_it() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.resend_chunk_size = 1;
+        let (application, mut received) = RecordingApplication::new();
+        let (application, replies) = application.with_replies();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        replies.set(session.clone()).ok().unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+        for i in 2..=20 {
+            session
+                .send_message(News::new(&format!("news {i}")))
+                .await
+                .unwrap();
+            counterparty.receive().await;
+        }
+
+        // the reply to the message is sent while we're still resending
+        counterparty
+            .send("2", |msg| {
+                msg.set(fix44::BEGIN_SEQ_NO, 1u64);
+                msg.set(fix44::END_SEQ_NO, 0u64);
+            })
+            .await;
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "new"))
+            .await;
+
+        let gap_fill = counterparty.receive().await;
+        assert_eq!(message_type(&gap_fill), "4");
+        let mut last_seq_num: u64 = gap_fill.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        for _ in 2..=20 {
+            let resent = counterparty.receive().await;
+            let seq_num: u64 = resent.header().get(fix44::MSG_SEQ_NUM).unwrap();
+            assert!(seq_num > last_seq_num);
+            last_seq_num = seq_num;
+            let poss_dup: bool = resent.header().get(fix44::POSS_DUP_FLAG).unwrap();
+            assert!(poss_dup);
+        }
+        assert_eq!(received.recv().await.unwrap().headline, "new");
+        let reply = counterparty.receive().await;
+        let seq_num: u64 = reply.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 21);
+        let headline: &str = reply.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "re: new");
+    }
+
+    #[tokio::test]
+    async fn test_logout_waits_for_peer_acknowledgement() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        counterparty.sync().await;
+
+        let logout = tokio::spawn({
+            let session = session.clone();
+            async move { session.logout("end of day".to_string()).await }
+        });
+
+        let message = counterparty.receive().await;
+        assert_eq!(message_type(&message), "5");
+        let text: &str = message.get(fix44::TEXT).unwrap();
+        assert_eq!(text, "end of day");
+        assert!(!logout.is_finished());
+
+        counterparty.send("5", |_| {}).await;
+        assert!(counterparty
+            .try_receive(Duration::from_secs(5))
+            .await
+            .is_none());
+        logout.await.unwrap().unwrap();
+        assert!(!session.should_reconnect().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_logout_disconnects_after_timeout() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.logout_timeout = 1;
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        counterparty.sync().await;
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            session.logout("end of day".to_string()),
+        )
+        .await
+        .expect("logout to complete after the timeout")
+        .unwrap();
+
+        assert_eq!(message_type(&counterparty.receive().await), "5");
+        assert!(counterparty
+            .try_receive(Duration::from_secs(5))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_peer_logout_is_acknowledged() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("5", |msg| msg.set(fix44::TEXT, "shutting down"))
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        assert!(counterparty
+            .try_receive(Duration::from_secs(5))
+            .await
+            .is_none());
+        assert!(session.should_reconnect().await.unwrap());
+    }
+
+    fn daily_schedule_starting_in(offset: chrono::Duration) -> ScheduleConfig {
+        let start_time = (Utc::now() + offset).time();
+        ScheduleConfig {
+            start_time,
+            end_time: start_time,
+            start_day: None,
+            end_day: None,
+            time_zone: Tz::UTC,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequence_numbers_are_reset_when_new_period_starts() {
+        let mut store = InMemoryMessageStore::default();
+        for seq_num in 1..=3 {
+            let message = build_message(
+                "FIX.4.4",
+                SESSION_COMP_ID,
+                PEER_COMP_ID,
+                seq_num,
+                News::new("old"),
+            )
+            .encode(&Config::default());
+            store.add(seq_num as u64, &message).await;
+            store.increment_sender_seq_number().await;
+            store.increment_target_seq_number().await;
+        }
+
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.reset_on_logon = false;
+        config.schedule = Some(daily_schedule_starting_in(chrono::Duration::milliseconds(
+            300,
+        )));
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            store,
+        )
+        .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut counterparty = Counterparty::connect(session).await;
+
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let seq_num: u64 = logon.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 1);
+        let next_expected: u64 = logon.get(fix44::NEXT_EXPECTED_MSG_SEQ_NUM).unwrap();
+        assert_eq!(next_expected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_connection_outside_session_time_is_dropped() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let mut schedule = daily_schedule_starting_in(chrono::Duration::hours(1));
+        schedule.end_time = (Utc::now() + chrono::Duration::hours(2)).time();
+        config.schedule = Some(schedule);
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Acceptor,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let (session_stream, mut peer) = duplex(1024);
+
+        let result =
+            FixConnection::accept(session_stream, session, Parser::default(), vec![]).await;
+
+        assert_eq!(result.err(), Some(SessionError::OutsideSessionTime));
+        let mut buf = vec![];
+        let read = timeout(Duration::from_secs(5), peer.read_buf(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, 0);
+    }
+
+    #[tokio::test]
+    async fn test_possible_duplicate_without_orig_sending_time_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "resent");
+                msg.set(fix44::POSS_DUP_FLAG, true);
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fix44::ORIG_SENDING_TIME.tag);
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::RequiredTagMissing);
+    }
+
+    #[tokio::test]
+    async fn test_orig_sending_time_after_sending_time_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "resent");
+                msg.set(fix44::POSS_DUP_FLAG, true);
+                msg.set(fix44::ORIG_SENDING_TIME, "29991231-23:59:59.000");
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(
+            reason,
+            fix44::SessionRejectReason::SendingtimeAccuracyProblem
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_of_processed_messages_are_checked_before_being_ignored() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, mut received) = logged_on_session(config).await;
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "original"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("original")));
+        let duplicate_seq_num = counterparty.next_seq_num - 1;
+
+        counterparty
+            .send_with_seq_num("B", duplicate_seq_num, |msg| {
+                msg.set(fix44::HEADLINE, "original");
+                msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+            })
+            .await;
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::RequiredTagMissing);
+        let ref_seq_num: u64 = reject.get(fix44::REF_SEQ_NUM).unwrap();
+        assert_eq!(ref_seq_num, duplicate_seq_num);
+
+        counterparty
+            .send_with_seq_num("B", duplicate_seq_num, |msg| {
+                msg.set(fix44::HEADLINE, "original");
+                msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+                msg.header_mut()
+                    .set(fix44::ORIG_SENDING_TIME, "29991231-23:59:59.000");
+            })
+            .await;
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(
+            reason,
+            fix44::SessionRejectReason::SendingtimeAccuracyProblem
+        );
+
+        // a valid duplicate is still ignored without a reply
+        counterparty
+            .send_with_seq_num("B", duplicate_seq_num, |msg| {
+                msg.set(fix44::HEADLINE, "original");
+                msg.header_mut().set(fix44::POSS_DUP_FLAG, true);
+                msg.header_mut()
+                    .set(fix44::ORIG_SENDING_TIME, "20240101-00:00:00.000");
+            })
+            .await;
+        assert!(counterparty
+            .try_receive(Duration::from_millis(200))
+            .await
+            .is_none());
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_message_for_another_comp_id_is_rejected_and_logged_out() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "misrouted");
+                msg.set(fix44::TARGET_COMP_ID, "SOMEONE-ELSE");
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::CompidProblem);
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fix44::TARGET_COMP_ID.tag);
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+    }
+
+    #[tokio::test]
+    async fn test_stale_sending_time_is_rejected_and_logged_out() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.max_latency = 60;
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        let sending_time = (Utc::now() - chrono::Duration::seconds(90))
+            .format("%Y%m%d-%H:%M:%S%.3f")
+            .to_string();
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "replayed");
+                msg.set(fix44::SENDING_TIME, sending_time.as_str());
+            })
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(
+            reason,
+            fix44::SessionRejectReason::SendingtimeAccuracyProblem
+        );
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+    }
+
+    #[tokio::test]
+    async fn test_incorrect_begin_string_is_logged_out_without_reject() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "wrong version");
+                msg.set(fix44::BEGIN_STRING, "FIX.4.2");
+            })
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let text: &str = logout.get(fix44::TEXT).unwrap();
+        assert!(text.contains("BeginString(8)"));
+    }
+
+    #[tokio::test]
+    async fn test_possible_duplicates_are_flagged_and_processed_ones_dropped() {
+        let (application, mut received) = RecordingApplication::new();
+        let (application, mut possible_duplicates) = application.with_possible_duplicates();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+        counterparty.logon().await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "original"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("original")));
+
+        // the same message again, which has been processed already
+        counterparty
+            .send_with_seq_num("B", 2, |msg| {
+                msg.set(fix44::HEADLINE, "original");
+                msg.set(fix44::POSS_DUP_FLAG, true);
+                msg.set(fix44::ORIG_SENDING_TIME, "20240101-00:00:00.000");
+            })
+            .await;
+        counterparty
+            .send("B", |msg| {
+                msg.set(fix44::HEADLINE, "maybe seen before");
+                msg.set(fix44::POSS_DUP_FLAG, true);
+                msg.set(fix44::ORIG_SENDING_TIME, "20240101-00:00:00.000");
+            })
+            .await;
+
+        assert_eq!(received.recv().await, Some(News::new("maybe seen before")));
+        assert_eq!(
+            possible_duplicates.recv().await,
+            Some(News::new("maybe seen before"))
+        );
+    }
+
+    /// Creates a session connected to a counterparty which hasn't responded to the logon yet.
+    async fn connected_session(config: SessionConfig) -> (SessionRef<News>, Counterparty) {
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            config,
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let counterparty = Counterparty::connect(session.clone()).await;
+        (session, counterparty)
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_queued_until_logged_on() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty) = connected_session(config).await;
+
+        let delivery = session.send_message(News::new("queued")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Queued);
+        assert_eq!(delivery.seq_num(), None);
+        counterparty.logon().await;
+
+        let news = counterparty.receive().await;
+        assert_eq!(message_type(&news), "B");
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 2);
+        assert!(news.header().get::<bool>(fix44::POSS_DUP_FLAG).is_err());
+        assert!(delivery.flushed().await);
+    }
+
+    #[tokio::test]
+    async fn test_sent_message_reports_its_sequence_number_once_flushed() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        counterparty.sync().await;
+
+        let delivery = session.send_message(News::new("written")).await.unwrap();
+        // the logon and the heartbeat in response to the sync came first
+        assert_eq!(delivery.outcome, DeliveryOutcome::Written { seq_num: 3 });
+        assert_eq!(delivery.seq_num(), Some(3));
+        assert!(delivery.flushed().await);
+
+        let news = counterparty.receive().await;
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 3);
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_rejected_while_not_logged_on() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.outbound_policy = OutboundPolicy::Reject;
+        let (session, mut counterparty) = connected_session(config).await;
+
+        let delivery = session.send_message(News::new("dropped")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Rejected);
+        assert!(!delivery.flushed().await);
+        counterparty.logon().await;
+        counterparty.sync().await;
+
+        let delivery = session.send_message(News::new("sent")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Written { seq_num: 3 });
+        let news = counterparty.receive().await;
+        let headline: &str = news.get(fix44::HEADLINE).unwrap();
+        assert_eq!(headline, "sent");
+        // the dropped message didn't use up a sequence number, the heartbeat was the second message
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 3);
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_resent_as_possible_duplicates_once_logged_on() {
+        let mut config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        config.outbound_policy = OutboundPolicy::ResendAsPossDup;
+        config.reset_on_logon = false;
+        let (session, mut counterparty) = connected_session(config).await;
+
+        let delivery = session.send_message(News::new("persisted")).await.unwrap();
+        assert_eq!(delivery.outcome, DeliveryOutcome::Persisted { seq_num: 2 });
+        counterparty.logon().await;
+
+        let news = counterparty.receive().await;
+        assert_eq!(message_type(&news), "B");
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 2);
+        let poss_dup: bool = news.header().get(fix44::POSS_DUP_FLAG).unwrap();
+        assert!(poss_dup);
+    }
+
+    #[tokio::test]
+    async fn test_operator_can_set_sequence_numbers() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, mut received) = logged_on_session(config).await;
+        counterparty.sync().await;
+
+        session.set_next_sender_seq_num(10).await.unwrap();
+        session.set_next_target_seq_num(20).await.unwrap();
+
+        session.send_message(News::new("outgoing")).await.unwrap();
+        let news = counterparty.receive().await;
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 10);
+
+        counterparty
+            .send_with_seq_num("B", 20, |msg| msg.set(fix44::HEADLINE, "incoming"))
+            .await;
+        assert_eq!(received.recv().await, Some(News::new("incoming")));
+
+        assert_eq!(
+            session.set_next_sender_seq_num(0).await,
+            Err(SessionError::InvalidSequenceNumber(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operator_can_send_sequence_reset() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty, _received) = logged_on_session(config).await;
+        counterparty.sync().await;
+
+        session.send_sequence_reset(50).await.unwrap();
+        let reset = counterparty.receive().await;
+        assert_eq!(message_type(&reset), "4");
+        assert!(!reset.get::<bool>(fix44::GAP_FILL_FLAG).unwrap());
+        let new_seq_no: u64 = reset.get(fix44::NEW_SEQ_NO).unwrap();
+        assert_eq!(new_seq_no, 50);
+        assert!(reset.header().get::<bool>(fix44::POSS_DUP_FLAG).is_err());
+
+        session
+            .send_message(News::new("after reset"))
+            .await
+            .unwrap();
+        let news = counterparty.receive().await;
+        let seq_num: u64 = news.header().get(fix44::MSG_SEQ_NUM).unwrap();
+        assert_eq!(seq_num, 50);
+
+        // sequence numbers can only move forward
+        assert_eq!(
+            session.send_sequence_reset(10).await,
+            Err(SessionError::InvalidSequenceNumber(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operator_can_request_resend() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (session, mut counterparty) = connected_session(config).await;
+
+        assert_eq!(
+            session.send_resend_request(1, 0).await,
+            Err(SessionError::NotLoggedOn)
+        );
+
+        counterparty.logon().await;
+        counterparty.sync().await;
+        session.send_resend_request(1, 2).await.unwrap();
+
+        let resend_request = counterparty.receive().await;
+        assert_eq!(message_type(&resend_request), "2");
+        let begin: u64 = resend_request.get(fix44::BEGIN_SEQ_NO).unwrap();
+        assert_eq!(begin, 1);
+        let end: u64 = resend_request.get(fix44::END_SEQ_NO).unwrap();
+        assert_eq!(end, 2);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_message_type_gets_business_reject() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty
+            .send("D", |msg| msg.set(fix44::CL_ORD_ID, "order-1"))
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "j");
+        let ref_seq_num: u64 = reject.get(fix44::REF_SEQ_NUM).unwrap();
+        assert_eq!(ref_seq_num, 2);
+        let ref_msg_type: &str = reject.get(fix44::REF_MSG_TYPE).unwrap();
+        assert_eq!(ref_msg_type, "D");
+        let reason: fix44::BusinessRejectReason =
+            reject.get(fix44::BUSINESS_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::BusinessRejectReason::UnsupportedMessageType);
+    }
+
+    async fn refusing_session(refusal: MessageRejection) -> (SessionRef<News>, Counterparty) {
+        let (application, _received) = RecordingApplication::new();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application.with_refusal(refusal)),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+        (session, counterparty)
+    }
+
+    #[tokio::test]
+    async fn test_message_that_fails_to_parse_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty.send("B", |_| {}).await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let ref_seq_num: u64 = reject.get(fix44::REF_SEQ_NUM).unwrap();
+        assert_eq!(ref_seq_num, 2);
+        let ref_msg_type: &str = reject.get(fix44::REF_MSG_TYPE).unwrap();
+        assert_eq!(ref_msg_type, "B");
+        let ref_tag_id: u32 = reject.get(fix44::REF_TAG_ID).unwrap();
+        assert_eq!(ref_tag_id, fix44::HEADLINE.tag);
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::RequiredTagMissing);
+    }
+
+    #[tokio::test]
+    async fn test_application_can_refuse_message_with_business_reject() {
+        let refusal = MessageRejection::business_reject(fix44::BusinessRejectReason::Other)
+            .with_business_reject_ref_id("news-1")
+            .with_text("not interested");
+        let (_session, mut counterparty) = refusing_session(refusal).await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "refused"))
+            .await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "j");
+        let ref_seq_num: u64 = reject.get(fix44::REF_SEQ_NUM).unwrap();
+        assert_eq!(ref_seq_num, 2);
+        let ref_id: &str = reject.get(fix44::BUSINESS_REJECT_REF_ID).unwrap();
+        assert_eq!(ref_id, "news-1");
+        let text: &str = reject.get(fix44::TEXT).unwrap();
+        assert_eq!(text, "not interested");
+    }
+
+    #[tokio::test]
+    async fn test_application_can_refuse_message_with_logout() {
+        let refusal = MessageRejection::logout("unknown account");
+        let (_session, mut counterparty) = refusing_session(refusal).await;
+
+        counterparty
+            .send("B", |msg| msg.set(fix44::HEADLINE, "refused"))
+            .await;
+
+        let logout = counterparty.receive().await;
+        assert_eq!(message_type(&logout), "5");
+        let text: &str = logout.get(fix44::TEXT).unwrap();
+        assert_eq!(text, "unknown account");
+    }
+
+    #[tokio::test]
+    async fn test_undefined_message_type_is_rejected() {
+        let config = session_config(SESSION_COMP_ID, PEER_COMP_ID, 0);
+        let (_session, mut counterparty, _received) = logged_on_session(config).await;
+
+        counterparty.send("ZZ", |_| {}).await;
+
+        let reject = counterparty.receive().await;
+        assert_eq!(message_type(&reject), "3");
+        let reason: fix44::SessionRejectReason = reject.get(fix44::SESSION_REJECT_REASON).unwrap();
+        assert_eq!(reason, fix44::SessionRejectReason::InvalidMsgtype);
+    }
+
+    #[tokio::test]
+    async fn test_admin_messages_go_through_application_hooks() {
+        let (application, _received) = RecordingApplication::new();
+        let (application, mut admin_received) = application.with_admin_hooks();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session).await;
+
+        let logon = counterparty.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let test_message: bool = logon.get(fix44::TEST_MESSAGE_INDICATOR).unwrap();
+        assert!(test_message);
+
+        counterparty
+            .send("A", |msg| {
+                msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+                msg.set(fix44::HEART_BT_INT, 30u64);
+                msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+            })
+            .await;
+        assert_eq!(admin_received.recv().await.as_deref(), Some("A"));
+
+        counterparty.sync().await;
+        assert_eq!(admin_received.recv().await.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_application_is_told_about_sent_messages() {
+        let (application, _received) = RecordingApplication::new();
+        let (application, mut sent) = application.with_sent();
+        let session = SessionRef::new(
+            session_config(SESSION_COMP_ID, PEER_COMP_ID, 0),
+            ConnectionType::Initiator,
+            ApplicationRef::new(application),
+            InMemoryMessageStore::default(),
+        )
+        .unwrap();
+        let mut counterparty = Counterparty::connect(session.clone()).await;
+        counterparty.logon().await;
+
+        session.send_message(News::new("outgoing")).await.unwrap();
+
+        assert_eq!(message_type(&counterparty.receive().await), "B");
+        assert_eq!(sent.recv().await, Some(News::new("outgoing")));
+    }
+}
@@ -0,0 +1,50 @@
+use tokio::sync::oneshot;
+
+/// What happened to an application message handed to the session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    /// The message was persisted and passed on to the connection.
+    Written { seq_num: u64 },
+    /// We weren't logged on, the message was persisted and will be resent as a possible duplicate,
+    /// see [OutboundPolicy::ResendAsPossDup](crate::config::OutboundPolicy::ResendAsPossDup).
+    Persisted { seq_num: u64 },
+    /// We weren't logged on, the message will be assigned a sequence number once we are,
+    /// see [OutboundPolicy::Queue](crate::config::OutboundPolicy::Queue).
+    /// Messages sent while we're resending messages to the peer are queued until we're done.
+    Queued,
+    /// We weren't logged on and the message was dropped,
+    /// see [OutboundPolicy::Reject](crate::config::OutboundPolicy::Reject).
+    Rejected,
+}
+
+/// The result of sending an application message.
+#[derive(Debug)]
+pub struct Delivery {
+    pub outcome: DeliveryOutcome,
+    flushed: oneshot::Receiver<()>,
+}
+
+impl Delivery {
+    pub(crate) fn new(outcome: DeliveryOutcome, flushed: oneshot::Receiver<()>) -> Self {
+        Self { outcome, flushed }
+    }
+
+    /// The MsgSeqNum(34) assigned to the message, if it has been persisted.
+    pub fn seq_num(&self) -> Option<u64> {
+        match self.outcome {
+            DeliveryOutcome::Written { seq_num } | DeliveryOutcome::Persisted { seq_num } => {
+                Some(seq_num)
+            }
+            DeliveryOutcome::Queued | DeliveryOutcome::Rejected => None,
+        }
+    }
+
+    /// Waits until the message has been written and flushed to the socket.
+    ///
+    /// Queued messages are flushed once we have logged on and are done resending. Returns `false` if the message
+    /// isn't going to be flushed as part of this send, because it was rejected, persisted for
+    /// a later resend, or the connection dropped before it could be written.
+    pub async fn flushed(self) -> bool {
+        self.flushed.await.is_ok()
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use hotfix_message::dict::Dictionary;
+use hotfix_message::message::{Config as MessageConfig, Message};
+use hotfix_message::{fix44, fixt11, FieldType, HardCodedFixFieldDefinition, ParseError, Part};
+
+use crate::config::SessionConfig;
+use crate::message::parser::RawFixMessage;
+use crate::message_utils::is_admin;
+use crate::session::DictionaryError;
+
+pub(crate) const FIXT_BEGIN_STRING: &str = "FIXT.1.1";
+
+/// The dictionaries messages are parsed and validated with.
+///
+/// FIX 4.x sessions use a single dictionary for everything. FIXT sessions use the FIXT.1.1
+/// dictionary for the header, the trailer and admin messages, and an application dictionary
+/// for the body of business messages, picked by the message's ApplVerID(1128) or by the
+/// DefaultApplVerID(1137) the peer sent on logon.
+///
+/// The built-in dictionaries are used unless the config points to QuickFIX XML specs.
+pub(crate) struct SessionDictionary {
+    transport: Dictionary,
+    application: Option<ApplicationDictionaries>,
+}
+
+struct ApplicationDictionaries {
+    /// The version we send on logon and use for our own messages.
+    default_appl_ver_id: fixt11::ApplVerId,
+    /// The version of the peer's messages that don't have an ApplVerID(1128).
+    peer_default_appl_ver_id: fixt11::ApplVerId,
+    /// Parsing a dictionary takes a while, so they're only loaded once needed.
+    dictionaries: HashMap<fixt11::ApplVerId, Dictionary>,
+}
+
+impl SessionDictionary {
+    pub(crate) fn new(config: &SessionConfig) -> Result<Self, DictionaryError> {
+        let dictionary = if config.begin_string == FIXT_BEGIN_STRING {
+            Self::fixt(config)?
+        } else {
+            Self::fix4(config)?
+        };
+        dictionary.check_credentials(config)?;
+        Ok(dictionary)
+    }
+
+    fn fix4(config: &SessionConfig) -> Result<Self, DictionaryError> {
+        let transport = match (&config.data_dictionary_path, config.begin_string.as_str()) {
+            (Some(path), _) => load_dictionary(path)?,
+            (None, "FIX.4.2") => Dictionary::fix42(),
+            (None, "FIX.4.4") => Dictionary::fix44(),
+            (None, begin_string) => {
+                return Err(DictionaryError::UnsupportedBeginString(
+                    begin_string.to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            transport,
+            application: None,
+        })
+    }
+
+    fn fixt(config: &SessionConfig) -> Result<Self, DictionaryError> {
+        let default_appl_ver_id = match &config.default_appl_ver_id {
+            Some(value) => fixt11::ApplVerId::deserialize(value.as_bytes())
+                .map_err(|_| DictionaryError::UnsupportedDefaultApplVerId(value.clone()))?,
+            None => fixt11::ApplVerId::Fix50sp2,
+        };
+        let mut application = ApplicationDictionaries {
+            default_appl_ver_id,
+            peer_default_appl_ver_id: default_appl_ver_id,
+            dictionaries: HashMap::new(),
+        };
+        if let Some(path) = &config.data_dictionary_path {
+            application
+                .dictionaries
+                .insert(default_appl_ver_id, load_dictionary(path)?);
+        }
+        if application.load(default_appl_ver_id).is_none() {
+            return Err(DictionaryError::UnsupportedDefaultApplVerId(
+                config.default_appl_ver_id.clone().unwrap_or_default(),
+            ));
+        }
+
+        let transport = match &config.transport_data_dictionary_path {
+            Some(path) => load_dictionary(path)?,
+            None => Dictionary::fixt11(),
+        };
+        Ok(Self {
+            transport,
+            application: Some(application),
+        })
+    }
+
+    /// Checks that the credentials sent on logon are defined by the dictionary.
+    fn check_credentials(&self, config: &SessionConfig) -> Result<(), DictionaryError> {
+        let credentials = [
+            (config.username.is_some(), fix44::USERNAME),
+            (config.password.is_some(), fix44::PASSWORD),
+            (config.new_password.is_some(), fix44::NEW_PASSWORD),
+        ];
+        for (configured, field) in credentials {
+            if configured && !self.has_field(field) {
+                return Err(DictionaryError::UnsupportedField(format!(
+                    "{}({})",
+                    field.name, field.tag
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The DefaultApplVerID(1137) to send on logon, only set for FIXT sessions.
+    pub(crate) fn default_appl_ver_id(&self) -> Option<fixt11::ApplVerId> {
+        self.application
+            .as_ref()
+            .map(|application| application.default_appl_ver_id)
+    }
+
+    /// Uses the peer's DefaultApplVerID(1137) for its messages from now on.
+    ///
+    /// Returns `false` if there's no dictionary for the version.
+    pub(crate) fn set_peer_default_appl_ver_id(&mut self, appl_ver_id: fixt11::ApplVerId) -> bool {
+        let Some(application) = &mut self.application else {
+            return true;
+        };
+        if application.load(appl_ver_id).is_none() {
+            return false;
+        }
+        application.peer_default_appl_ver_id = appl_ver_id;
+        true
+    }
+
+    /// Parses a message, falling back to the default application dictionary
+    /// if the one asked for by the message's ApplVerID(1128) isn't supported.
+    pub(crate) fn parse(
+        &mut self,
+        config: &MessageConfig,
+        raw: &RawFixMessage,
+    ) -> Result<Message, ParseError> {
+        let Some(application) = &mut self.application else {
+            return Message::from_bytes(config, &self.transport, raw.as_bytes());
+        };
+        let message_type = raw.get_raw(fix44::MSG_TYPE.tag).unwrap_or_default();
+        if std::str::from_utf8(message_type).is_ok_and(is_admin) {
+            return Message::from_bytes(config, &self.transport, raw.as_bytes());
+        }
+
+        let appl_ver_id = raw
+            .get_raw(fixt11::APPL_VER_ID.tag)
+            .and_then(|value| fixt11::ApplVerId::deserialize(value).ok())
+            .filter(|appl_ver_id| application.load(*appl_ver_id).is_some())
+            .unwrap_or(application.peer_default_appl_ver_id);
+        let dictionary = &application.dictionaries[&appl_ver_id];
+        Message::from_bytes_with_dictionaries(config, &self.transport, dictionary, raw.as_bytes())
+    }
+
+    /// The dictionary defining the header and the trailer.
+    pub(crate) fn transport(&self) -> &Dictionary {
+        &self.transport
+    }
+
+    /// Whether the session's FIX version has the given field, so it can be sent in admin messages.
+    pub(crate) fn has_field(&self, field: &HardCodedFixFieldDefinition) -> bool {
+        self.transport.field_by_tag(field.tag).is_some()
+    }
+
+    /// The dictionary defining the body of the message.
+    ///
+    /// Returns `None` if the message has an ApplVerID(1128) we don't have a dictionary for.
+    pub(crate) fn application(&self, message: &Message) -> Option<&Dictionary> {
+        let Some(application) = &self.application else {
+            return Some(&self.transport);
+        };
+        let message_type: &str = message.header().get(fix44::MSG_TYPE).unwrap_or_default();
+        if is_admin(message_type) {
+            return Some(&self.transport);
+        }
+
+        let appl_ver_id = match message.header().get_raw(fixt11::APPL_VER_ID) {
+            Some(value) => fixt11::ApplVerId::deserialize(value).ok()?,
+            None => application.peer_default_appl_ver_id,
+        };
+        application.dictionaries.get(&appl_ver_id)
+    }
+}
+
+impl ApplicationDictionaries {
+    fn load(&mut self, appl_ver_id: fixt11::ApplVerId) -> Option<&Dictionary> {
+        match self.dictionaries.entry(appl_ver_id) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => Some(entry.insert(load_application_dictionary(appl_ver_id)?)),
+        }
+    }
+}
+
+fn load_application_dictionary(appl_ver_id: fixt11::ApplVerId) -> Option<Dictionary> {
+    match appl_ver_id {
+        fixt11::ApplVerId::Fix42 => Some(Dictionary::fix42()),
+        fixt11::ApplVerId::Fix44 => Some(Dictionary::fix44()),
+        fixt11::ApplVerId::Fix50 => Some(Dictionary::fix50()),
+        fixt11::ApplVerId::Fix50sp1 => Some(Dictionary::fix50sp1()),
+        fixt11::ApplVerId::Fix50sp2 => Some(Dictionary::fix50sp2()),
+        _ => None,
+    }
+}
+
+fn load_dictionary(path: &Path) -> Result<Dictionary, DictionaryError> {
+    let spec = fs::read_to_string(path).map_err(|source| DictionaryError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Dictionary::from_quickfix_spec(&spec).map_err(|err| DictionaryError::Parse {
+        path: path.to_path_buf(),
+        reason: format!("{err:?}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_utils::session_config;
+
+    fn fixt_config(default_appl_ver_id: &str) -> SessionConfig {
+        SessionConfig {
+            begin_string: FIXT_BEGIN_STRING.to_string(),
+            default_appl_ver_id: Some(default_appl_ver_id.to_string()),
+            ..session_config("SENDER", "TARGET", 0)
+        }
+    }
+
+    #[test]
+    fn test_unknown_begin_string_is_an_error() {
+        let config = SessionConfig {
+            begin_string: "FIX.4.9".to_string(),
+            ..session_config("SENDER", "TARGET", 0)
+        };
+
+        let result = SessionDictionary::new(&config);
+
+        assert!(matches!(
+            result,
+            Err(DictionaryError::UnsupportedBeginString(begin_string)) if begin_string == "FIX.4.9"
+        ));
+    }
+
+    #[test]
+    fn test_credentials_without_fields_in_dictionary_are_an_error() {
+        let config = SessionConfig {
+            begin_string: "FIX.4.2".to_string(),
+            username: Some("trader".to_string()),
+            ..session_config("SENDER", "TARGET", 0)
+        };
+
+        let result = SessionDictionary::new(&config);
+
+        assert!(matches!(
+            result,
+            Err(DictionaryError::UnsupportedField(field)) if field == "Username(553)"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_default_appl_ver_id_is_an_error() {
+        let result = SessionDictionary::new(&fixt_config("FIX99"));
+
+        assert!(matches!(
+            result,
+            Err(DictionaryError::UnsupportedDefaultApplVerId(value)) if value == "FIX99"
+        ));
+    }
+
+    #[test]
+    fn test_default_appl_ver_id_without_dictionary_is_an_error() {
+        // FIX.2.7 is a valid ApplVerID(1128), but there's no dictionary for it
+        let result = SessionDictionary::new(&fixt_config("0"));
+
+        assert!(matches!(
+            result,
+            Err(DictionaryError::UnsupportedDefaultApplVerId(value)) if value == "0"
+        ));
+    }
+
+    #[test]
+    fn test_unreadable_data_dictionary_is_an_error() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("missing/FIX44.xml");
+        let config = SessionConfig {
+            data_dictionary_path: Some(path.clone()),
+            ..session_config("SENDER", "TARGET", 0)
+        };
+
+        let result = SessionDictionary::new(&config);
+
+        assert!(matches!(result, Err(DictionaryError::Read { path: p, .. }) if p == path));
+    }
+
+    #[test]
+    fn test_unparsable_data_dictionary_is_an_error() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let config = SessionConfig {
+            data_dictionary_path: Some(path.clone()),
+            ..fixt_config("9")
+        };
+
+        let result = SessionDictionary::new(&config);
+
+        assert!(matches!(result, Err(DictionaryError::Parse { path: p, .. }) if p == path));
+    }
+
+    #[test]
+    fn test_unreadable_transport_data_dictionary_is_an_error() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("missing/FIXT11.xml");
+        let config = SessionConfig {
+            transport_data_dictionary_path: Some(path.clone()),
+            ..fixt_config("9")
+        };
+
+        let result = SessionDictionary::new(&config);
+
+        assert!(matches!(result, Err(DictionaryError::Read { path: p, .. }) if p == path));
+    }
+}
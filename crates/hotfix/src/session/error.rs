@@ -0,0 +1,71 @@
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned when interacting with a session.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SessionError {
+    /// The session task has stopped and can no longer handle requests.
+    #[error("the session has stopped")]
+    SessionStopped,
+    /// The application task has stopped, usually because one of its callbacks panicked.
+    #[error("the application has stopped")]
+    ApplicationStopped,
+    /// The request needs the session to be logged on.
+    #[error("the session isn't logged on")]
+    NotLoggedOn,
+    /// The session already has a connection to the peer.
+    #[error("the session is already connected")]
+    AlreadyConnected,
+    /// The connection was made outside of the session's schedule.
+    #[error("the session is outside of its session time")]
+    OutsideSessionTime,
+    /// The sequence number isn't valid for the request.
+    #[error("invalid sequence number {0}")]
+    InvalidSequenceNumber(u64),
+    /// There's no session configured between the given comp IDs.
+    #[error("no session configured from {sender_comp_id} to {target_comp_id}")]
+    SessionNotFound {
+        sender_comp_id: String,
+        target_comp_id: String,
+    },
+}
+
+/// Errors returned when a session can't be created from its config.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The dictionaries of the session can't be loaded.
+    #[error(transparent)]
+    Dictionary(#[from] DictionaryError),
+    /// A secret sent on logon can't be read.
+    #[error("failed to read {field}: {source}")]
+    Secret {
+        field: &'static str,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Errors returned when loading the dictionaries of a session's config.
+#[derive(Debug, thiserror::Error)]
+pub enum DictionaryError {
+    /// There's no built-in dictionary for the BeginString(8) and no data dictionary is configured.
+    #[error("unsupported BeginString(8)={0}")]
+    UnsupportedBeginString(String),
+    /// The DefaultApplVerID(1137) isn't a valid value or there's no dictionary for it.
+    #[error("unsupported DefaultApplVerID(1137)={0}")]
+    UnsupportedDefaultApplVerId(String),
+    /// A field configured to be sent on logon isn't defined by the dictionary,
+    /// such as the credentials of FIX 4.2 sessions.
+    #[error("{0} is configured, but the dictionary doesn't define it")]
+    UnsupportedField(String),
+    /// The data dictionary file couldn't be read.
+    #[error("failed to read data dictionary {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The data dictionary file isn't a valid QuickFIX spec.
+    #[error("failed to parse data dictionary {}: {reason}", path.display())]
+    Parse { path: PathBuf, reason: String },
+}
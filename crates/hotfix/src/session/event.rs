@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+
+/// Something that happened to the session, published to subscribers of [SessionRef::subscribe].
+///
+/// [SessionRef::subscribe]: crate::session::SessionRef::subscribe
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionEvent {
+    pub time: DateTime<Utc>,
+    pub kind: SessionEventKind,
+}
+
+impl SessionEvent {
+    pub(crate) fn new(kind: SessionEventKind) -> Self {
+        Self {
+            time: Utc::now(),
+            kind,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEventKind {
+    /// A connection to the peer has been established.
+    Connected,
+    /// We have sent our logon, either to initiate the session or in response to the peer's.
+    LogonSent,
+    /// Both sides have logged on and the session is active.
+    LoggedOn,
+    /// The peer has sent a logout, either to log us out or to acknowledge our logout.
+    LogoutReceived { reason: String },
+    /// The connection to the peer has been closed.
+    Disconnected { reason: String },
+    /// We have received a sequence number higher than expected and asked the peer to resend the gap.
+    GapDetected { expected: u64, received: u64 },
+    /// The peer has resent everything up to and including the given sequence number.
+    ResendCompleted { end_seq_num: u64 },
+    /// The peer has rejected one of our messages at the session level.
+    RejectReceived {
+        ref_seq_num: u64,
+        text: Option<String>,
+    },
+}
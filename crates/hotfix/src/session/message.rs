@@ -2,6 +2,7 @@ use tokio::sync::oneshot;
 
 use crate::actors::socket_writer::WriterRef;
 use crate::message::parser::RawFixMessage;
+use crate::session::{Delivery, SessionError};
 
 #[derive(Debug)]
 pub enum SessionMessage<M> {
@@ -9,12 +10,54 @@ pub enum SessionMessage<M> {
     FixMessageReceived(RawFixMessage),
     /// Ask the session to send a new heartbeat.
     SendHeartbeat,
+    /// Let the session know we haven't heard from the peer for too long.
+    PeerTimeout,
     /// Ask the session to send a message from the application.
-    SendMessage(M),
-    /// Let the session know we've been disconnected.
-    Disconnected(String),
-    /// Register a new writer connected to the other side.
-    Connected(WriterRef),
+    SendMessage {
+        message: M,
+        responder: oneshot::Sender<Delivery>,
+    },
+    /// Ask the session to send a message from the application, without responding.
+    PostMessage(M),
+    /// Let the session know the given connection has been closed.
+    Disconnected { connection_id: u64, reason: String },
+    /// Register a new writer connected to the other side, which the session may refuse.
+    Connected {
+        writer: WriterRef,
+        responder: oneshot::Sender<Result<(), SessionError>>,
+    },
     /// Ask the session whether we should attempt to reconnect.
     ShouldReconnect(oneshot::Sender<bool>),
+    /// Ask the session to log out, responding once the connection is closed.
+    Logout {
+        reason: String,
+        responder: oneshot::Sender<()>,
+    },
+    /// Let the session know the peer hasn't responded to our logout in time.
+    LogoutTimeout,
+    /// Let the session know the session period may have started or ended.
+    ScheduleCheck,
+    /// Ask the session to resend the next chunk of messages the peer has asked for.
+    ContinueResend,
+    /// Ask the session to change the sequence number of the next message we send.
+    SetNextSenderSeqNum {
+        seq_num: u64,
+        responder: oneshot::Sender<Result<(), SessionError>>,
+    },
+    /// Ask the session to change the sequence number it expects on the next message from the peer.
+    SetNextTargetSeqNum {
+        seq_num: u64,
+        responder: oneshot::Sender<Result<(), SessionError>>,
+    },
+    /// Ask the session to send a SequenceReset(4) in reset mode, moving our sequence numbers forward.
+    SendSequenceReset {
+        new_seq_no: u64,
+        responder: oneshot::Sender<Result<(), SessionError>>,
+    },
+    /// Ask the session to request a resend of the given range from the peer.
+    SendResendRequest {
+        begin: u64,
+        end: u64,
+        responder: oneshot::Sender<Result<(), SessionError>>,
+    },
 }
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use crate::config::ScheduleConfig;
+
+impl ScheduleConfig {
+    /// Whether the session is expected to be logged on at the given time.
+    pub(crate) fn is_in_session(&self, now: DateTime<Utc>) -> bool {
+        let (_, end) = self.latest_period(now);
+        self.local_time(now) < end
+    }
+
+    /// The start of the latest session period that has started by the given time.
+    pub(crate) fn period_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let (start, _) = self.latest_period(now);
+        self.to_utc(start)
+    }
+
+    /// The next time the session either starts or ends.
+    pub(crate) fn next_transition(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let (start, end) = self.latest_period(now);
+        if self.local_time(now) < end {
+            self.to_utc(end)
+        } else {
+            self.to_utc(start + self.cycle())
+        }
+    }
+
+    fn weekdays(&self) -> Option<(Weekday, Weekday)> {
+        self.start_day.zip(self.end_day)
+    }
+
+    /// How often the session period repeats.
+    fn cycle(&self) -> Duration {
+        match self.weekdays() {
+            Some(_) => Duration::days(7),
+            None => Duration::days(1),
+        }
+    }
+
+    /// How long each session period lasts, matching start and end times make it run for the whole cycle.
+    fn length(&self) -> Duration {
+        let days = match self.weekdays() {
+            Some((start_day, end_day)) => {
+                let days =
+                    (7 + end_day.num_days_from_monday() - start_day.num_days_from_monday()) % 7;
+                Duration::days(days as i64)
+            }
+            None => Duration::zero(),
+        };
+        let length = days + (self.end_time - self.start_time);
+        if length <= Duration::zero() {
+            length + self.cycle()
+        } else {
+            length
+        }
+    }
+
+    /// The start and end of the latest period that has started by the given time, in local time.
+    fn latest_period(&self, now: DateTime<Utc>) -> (NaiveDateTime, NaiveDateTime) {
+        let local_now = self.local_time(now);
+        let start_date = match self.weekdays() {
+            Some((start_day, _)) => {
+                let days_since_start = (7 + local_now.weekday().num_days_from_monday()
+                    - start_day.num_days_from_monday())
+                    % 7;
+                local_now.date() - Duration::days(days_since_start as i64)
+            }
+            None => local_now.date(),
+        };
+
+        let mut start = start_date.and_time(self.start_time);
+        if start > local_now {
+            start -= self.cycle();
+        }
+        (start, start + self.length())
+    }
+
+    fn local_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        time.with_timezone(&self.time_zone).naive_local()
+    }
+
+    fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self.time_zone.from_local_datetime(&local).earliest() {
+            Some(time) => time.with_timezone(&Utc),
+            // the local time is skipped by a DST change, the clocks move forward by an hour at most
+            None => self.to_utc(local + Duration::hours(1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveTime, TimeZone, Utc, Weekday};
+    use chrono_tz::Tz;
+
+    use crate::config::ScheduleConfig;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn utc(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // the first of January 2024 was a Monday
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn daily(start_time: NaiveTime, end_time: NaiveTime) -> ScheduleConfig {
+        ScheduleConfig {
+            start_time,
+            end_time,
+            start_day: None,
+            end_day: None,
+            time_zone: Tz::UTC,
+        }
+    }
+
+    #[test]
+    fn test_daily_session() {
+        let schedule = daily(time(8, 0), time(17, 0));
+
+        assert!(!schedule.is_in_session(utc(2, 7, 59)));
+        assert!(schedule.is_in_session(utc(2, 8, 0)));
+        assert!(schedule.is_in_session(utc(2, 16, 59)));
+        assert!(!schedule.is_in_session(utc(2, 17, 0)));
+
+        assert_eq!(schedule.period_start(utc(2, 12, 0)), utc(2, 8, 0));
+        assert_eq!(schedule.period_start(utc(2, 7, 0)), utc(1, 8, 0));
+        assert_eq!(schedule.next_transition(utc(2, 12, 0)), utc(2, 17, 0));
+        assert_eq!(schedule.next_transition(utc(2, 18, 0)), utc(3, 8, 0));
+    }
+
+    #[test]
+    fn test_daily_session_over_midnight() {
+        let schedule = daily(time(22, 0), time(6, 0));
+
+        assert!(schedule.is_in_session(utc(2, 23, 0)));
+        assert!(schedule.is_in_session(utc(3, 5, 0)));
+        assert!(!schedule.is_in_session(utc(3, 12, 0)));
+        assert_eq!(schedule.period_start(utc(3, 5, 0)), utc(2, 22, 0));
+    }
+
+    #[test]
+    fn test_daily_session_without_break() {
+        let schedule = daily(time(17, 0), time(17, 0));
+
+        assert!(schedule.is_in_session(utc(2, 16, 59)));
+        assert!(schedule.is_in_session(utc(2, 17, 0)));
+        assert_eq!(schedule.period_start(utc(2, 16, 59)), utc(1, 17, 0));
+        assert_eq!(schedule.period_start(utc(2, 17, 0)), utc(2, 17, 0));
+        assert_eq!(schedule.next_transition(utc(2, 12, 0)), utc(2, 17, 0));
+    }
+
+    #[test]
+    fn test_weekly_session_in_time_zone() {
+        // 24x5 FX session, New York is five hours behind UTC in January
+        let schedule = ScheduleConfig {
+            start_time: time(17, 5),
+            end_time: time(17, 0),
+            start_day: Some(Weekday::Sun),
+            end_day: Some(Weekday::Fri),
+            time_zone: Tz::America__New_York,
+        };
+
+        // Sunday 7th, 22:05 UTC is 17:05 in New York
+        assert!(!schedule.is_in_session(utc(7, 22, 0)));
+        assert!(schedule.is_in_session(utc(7, 22, 5)));
+        assert!(schedule.is_in_session(utc(10, 12, 0)));
+        assert!(schedule.is_in_session(utc(12, 21, 59)));
+        assert!(!schedule.is_in_session(utc(12, 22, 0)));
+        assert!(!schedule.is_in_session(utc(13, 12, 0)));
+
+        assert_eq!(schedule.period_start(utc(10, 12, 0)), utc(7, 22, 5));
+        assert_eq!(schedule.next_transition(utc(10, 12, 0)), utc(12, 22, 0));
+        assert_eq!(schedule.next_transition(utc(13, 12, 0)), utc(14, 22, 5));
+    }
+}
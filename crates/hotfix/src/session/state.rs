@@ -1,5 +1,6 @@
 use crate::actors::socket_writer::WriterRef;
 use crate::message::parser::RawFixMessage;
+use tokio::sync::oneshot;
 use tracing::{debug, error};
 
 pub enum SessionState {
@@ -7,6 +8,8 @@ pub enum SessionState {
     AwaitingLogon { writer: WriterRef, logon_sent: bool },
     /// The session is active, we have connected and mutually logged on.
     Active { writer: WriterRef },
+    /// We have sent a logout message and await the peer's response.
+    AwaitingLogout { writer: WriterRef, reconnect: bool },
     /// The peer has logged us out.
     LoggedOut { reconnect: bool },
     /// The TCP connection has been dropped.
@@ -16,18 +19,31 @@ pub enum SessionState {
 impl SessionState {
     pub fn should_reconnect(&self) -> bool {
         match self {
-            SessionState::Disconnected { reconnect, .. } => *reconnect,
+            SessionState::Disconnected { reconnect, .. }
+            | SessionState::LoggedOut { reconnect } => *reconnect,
             _ => true,
         }
     }
 
-    pub async fn send_message(&mut self, message_type: &[u8], message: RawFixMessage) {
+    pub fn is_awaiting_logout(&self) -> bool {
+        matches!(self, SessionState::AwaitingLogout { .. })
+    }
+
+    /// Writes the message to the connection if the state allows it.
+    ///
+    /// `flushed` is notified once the message has been flushed, it's dropped if the message isn't written.
+    pub async fn send_message(
+        &mut self,
+        message_type: &[u8],
+        message: RawFixMessage,
+        flushed: Option<oneshot::Sender<()>>,
+    ) {
         match self {
-            Self::Active { writer } => {
+            Self::Active { writer } | Self::AwaitingLogout { writer, .. } => {
                 if message_type == b"A" {
                     error!("logon message is invalid for active sessions")
                 } else {
-                    writer.send_raw_message(message).await
+                    writer.send_raw_message(message, flushed).await
                 }
             }
             Self::AwaitingLogon {
@@ -38,9 +54,12 @@ impl SessionState {
                     if *logon_sent {
                         error!("trying to send logon twice");
                     } else {
-                        writer.send_raw_message(message).await;
+                        writer.send_raw_message(message, flushed).await;
                         *logon_sent = true;
                     }
+                } else if message_type == b"5" {
+                    // a logout can tell the peer why its logon wasn't accepted
+                    writer.send_raw_message(message, flushed).await
                 } else {
                     debug!("received message while in logon state - won't send")
                 }
@@ -51,7 +70,9 @@ impl SessionState {
 
     pub async fn disconnect(&self) {
         match self {
-            Self::Active { writer } => writer.disconnect().await,
+            Self::Active { writer }
+            | Self::AwaitingLogon { writer, .. }
+            | Self::AwaitingLogout { writer, .. } => writer.disconnect().await,
             _ => debug!("disconnecting an already disconnected session"),
         }
     }
@@ -0,0 +1,352 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use hotfix_message::dict::{Dictionary, FieldLocation, FixDatatype};
+use hotfix_message::field_types::Timestamp;
+use hotfix_message::message::Message;
+use hotfix_message::validation::{MessageValidator, ValidationSettings};
+use hotfix_message::{fix44, FieldType, HardCodedFixFieldDefinition, Part};
+
+use crate::config::SessionConfig;
+use crate::message::reject::Reject;
+
+const HEADER_FIELDS: [&HardCodedFixFieldDefinition; 3] = [
+    fix44::SENDER_COMP_ID,
+    fix44::TARGET_COMP_ID,
+    fix44::SENDING_TIME,
+];
+
+const FLAG_FIELDS: [&HardCodedFixFieldDefinition; 3] = [
+    fix44::POSS_DUP_FLAG,
+    fix44::GAP_FILL_FLAG,
+    fix44::RESET_SEQ_NUM_FLAG,
+];
+
+/// A header problem the session can't carry on after.
+#[derive(Debug)]
+pub(crate) enum HeaderError {
+    /// The message is for a different FIX version, so the peer is logged out without a reject.
+    BeginString(String),
+    /// The message is rejected, then the peer is logged out.
+    Rejected(Reject),
+}
+
+fn required_admin_fields(message_type: &str) -> &'static [&'static HardCodedFixFieldDefinition] {
+    match message_type {
+        "1" => &[fix44::TEST_REQ_ID],
+        "2" => &[fix44::BEGIN_SEQ_NO, fix44::END_SEQ_NO],
+        "3" => &[fix44::REF_SEQ_NUM],
+        "4" => &[fix44::NEW_SEQ_NO],
+        "A" => &[fix44::ENCRYPT_METHOD, fix44::HEART_BT_INT],
+        _ => &[],
+    }
+}
+
+/// Checks the message for problems that require a session-level reject.
+///
+/// The header is checked against the transport dictionary and the body against the
+/// application dictionary, which are the same unless it's a FIXT session. Without
+/// validation settings, only undefined tags are checked against the dictionaries.
+pub(crate) fn validate_message(
+    message: &Message,
+    transport_dictionary: &Dictionary,
+    application_dictionary: &Dictionary,
+    settings: Option<&ValidationSettings>,
+    message_type: &str,
+    msg_seq_num: u64,
+) -> Result<(), Reject> {
+    let reject = |field: &HardCodedFixFieldDefinition, reason, text: String| {
+        Reject::new(msg_seq_num)
+            .with_ref_msg_type(message_type)
+            .with_ref_tag_id(field.tag)
+            .with_session_reject_reason(reason)
+            .with_text(&text)
+    };
+
+    for field in HEADER_FIELDS {
+        if message.header().get_raw(field).is_none() {
+            return Err(reject(
+                field,
+                fix44::SessionRejectReason::RequiredTagMissing,
+                format!("required tag missing: {}({})", field.name, field.tag),
+            ));
+        }
+    }
+    if message
+        .header()
+        .get::<Timestamp>(fix44::SENDING_TIME)
+        .is_err()
+    {
+        return Err(reject(
+            fix44::SENDING_TIME,
+            fix44::SessionRejectReason::IncorrectDataFormatForValue,
+            "incorrect data format for SendingTime(52)".to_string(),
+        ));
+    }
+
+    if application_dictionary
+        .message_by_msgtype(message_type)
+        .is_none()
+    {
+        return Err(reject(
+            fix44::MSG_TYPE,
+            fix44::SessionRejectReason::InvalidMsgtype,
+            format!("invalid MsgType(35)={message_type}"),
+        ));
+    }
+
+    if let Some(settings) = settings {
+        MessageValidator::with_dictionaries(transport_dictionary, application_dictionary, settings)
+            .validate(message)
+            .map_err(|err| {
+                let reject = Reject::new(msg_seq_num)
+                    .with_ref_msg_type(message_type)
+                    .with_session_reject_reason(err.session_reject_reason())
+                    .with_text(&err.to_string());
+                match err.tag() {
+                    Some(tag) => reject.with_ref_tag_id(tag.get()),
+                    None => reject,
+                }
+            })?;
+    } else {
+        let header_tags = message
+            .header()
+            .get_field_map()
+            .fields
+            .keys()
+            .map(|tag| (tag, transport_dictionary));
+        let body_tags = message
+            .get_field_map()
+            .fields
+            .keys()
+            .map(|tag| (tag, application_dictionary));
+        for (tag, dictionary) in header_tags.chain(body_tags) {
+            if dictionary.field_by_tag(tag.get()).is_none() {
+                return Err(Reject::new(msg_seq_num)
+                    .with_ref_msg_type(message_type)
+                    .with_ref_tag_id(tag.get())
+                    .with_session_reject_reason(fix44::SessionRejectReason::InvalidTagNumber)
+                    .with_text(&format!("invalid tag number: {}", tag.get())));
+            }
+        }
+    }
+
+    for field in required_admin_fields(message_type) {
+        let Some(value) = message.get_raw(field) else {
+            return Err(reject(
+                field,
+                fix44::SessionRejectReason::RequiredTagMissing,
+                format!("required tag missing: {}({})", field.name, field.tag),
+            ));
+        };
+        let is_numeric = matches!(
+            field.data_type,
+            FixDatatype::Int | FixDatatype::SeqNum | FixDatatype::Length
+        );
+        if is_numeric && u64::deserialize(value).is_err() {
+            return Err(reject(
+                field,
+                fix44::SessionRejectReason::IncorrectDataFormatForValue,
+                format!("incorrect data format for {}({})", field.name, field.tag),
+            ));
+        }
+    }
+
+    for field in FLAG_FIELDS {
+        let value = match field.location {
+            FieldLocation::Header => message.header().get_raw(field),
+            _ => message.get_raw(field),
+        };
+        if value.is_some_and(|v| bool::deserialize(v).is_err()) {
+            return Err(reject(
+                field,
+                fix44::SessionRejectReason::ValueIsIncorrect,
+                format!("value is incorrect for {}({})", field.name, field.tag),
+            ));
+        }
+    }
+
+    validate_orig_sending_time(message, message_type, msg_seq_num)
+}
+
+/// Checks the OrigSendingTime(122) of possible duplicates, which is also done for
+/// duplicates of messages we have already processed before ignoring them.
+pub(crate) fn validate_orig_sending_time(
+    message: &Message,
+    message_type: &str,
+    msg_seq_num: u64,
+) -> Result<(), Reject> {
+    let reject = |reason, text: &str| {
+        Reject::new(msg_seq_num)
+            .with_ref_msg_type(message_type)
+            .with_ref_tag_id(fix44::ORIG_SENDING_TIME.tag)
+            .with_session_reject_reason(reason)
+            .with_text(text)
+    };
+
+    // sequence resets are allowed without the original sending time
+    let possible_duplicate: bool = message.header().get(fix44::POSS_DUP_FLAG).unwrap_or(false);
+    if !possible_duplicate || message_type == "4" {
+        return Ok(());
+    }
+    let Ok(orig_sending_time) = message.header().get::<Timestamp>(fix44::ORIG_SENDING_TIME) else {
+        return Err(reject(
+            fix44::SessionRejectReason::RequiredTagMissing,
+            "required tag missing: OrigSendingTime(122)",
+        ));
+    };
+    if let Ok(sending_time) = message.header().get::<Timestamp>(fix44::SENDING_TIME) {
+        if (orig_sending_time.date(), orig_sending_time.time())
+            > (sending_time.date(), sending_time.time())
+        {
+            return Err(reject(
+                fix44::SessionRejectReason::SendingtimeAccuracyProblem,
+                "OrigSendingTime(122) is later than SendingTime(52)",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the message was meant for this session and isn't stale.
+///
+/// The CompIDs of logons are checked by [validate_logon] instead, as there's no session
+/// to reject them in yet. Missing or malformed header fields are left to [validate_message].
+pub(crate) fn validate_header(
+    message: &Message,
+    config: &SessionConfig,
+    message_type: &str,
+    msg_seq_num: u64,
+) -> Result<(), HeaderError> {
+    let begin_string: &str = message
+        .header()
+        .get(fix44::BEGIN_STRING)
+        .unwrap_or_default();
+    if begin_string != config.begin_string {
+        return Err(HeaderError::BeginString(format!(
+            "incorrect BeginString(8), expecting {} but received {begin_string}",
+            config.begin_string
+        )));
+    }
+
+    let reject = |field: &HardCodedFixFieldDefinition, reason, text: String| {
+        HeaderError::Rejected(
+            Reject::new(msg_seq_num)
+                .with_ref_msg_type(message_type)
+                .with_ref_tag_id(field.tag)
+                .with_session_reject_reason(reason)
+                .with_text(&text),
+        )
+    };
+
+    if message_type != "A" {
+        let sender_comp_id = message.header().get::<&str>(fix44::SENDER_COMP_ID);
+        if sender_comp_id.is_ok_and(|id| id != config.target_comp_id) {
+            return Err(reject(
+                fix44::SENDER_COMP_ID,
+                fix44::SessionRejectReason::CompidProblem,
+                format!(
+                    "incorrect SenderCompID(49), expecting {}",
+                    config.target_comp_id
+                ),
+            ));
+        }
+        let target_comp_id = message.header().get::<&str>(fix44::TARGET_COMP_ID);
+        if target_comp_id.is_ok_and(|id| id != config.sender_comp_id) {
+            return Err(reject(
+                fix44::TARGET_COMP_ID,
+                fix44::SessionRejectReason::CompidProblem,
+                format!(
+                    "incorrect TargetCompID(56), expecting {}",
+                    config.sender_comp_id
+                ),
+            ));
+        }
+    }
+
+    let sending_time = message
+        .header()
+        .get::<Timestamp>(fix44::SENDING_TIME)
+        .ok()
+        .and_then(|sending_time| to_utc(&sending_time));
+    if let Some(sending_time) = sending_time {
+        let latency = (Utc::now() - sending_time).abs();
+        if latency > Duration::seconds(config.max_latency as i64) {
+            return Err(reject(
+                fix44::SENDING_TIME,
+                fix44::SessionRejectReason::SendingtimeAccuracyProblem,
+                format!(
+                    "SendingTime(52) is {}s off, allowing at most {}s",
+                    latency.num_seconds(),
+                    config.max_latency
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn to_utc(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    let (date, time) = (timestamp.date(), timestamp.time());
+    let date = NaiveDate::from_ymd_opt(date.year() as i32, date.month(), date.day())?;
+    let time =
+        NaiveTime::from_hms_milli_opt(time.hour(), time.minute(), time.second(), time.milli())?;
+    Some(Utc.from_utc_datetime(&NaiveDateTime::new(date, time)))
+}
+
+/// Checks the peer's logon against our session config.
+///
+/// `logon_sent` tells whether this is the response to our own logon, in which case
+/// the peer needs to agree with what we have sent. `next_sender_seq_num` is the
+/// sequence number the peer should expect from us next, not counting our logon response.
+///
+/// The error is the reason to log the peer out with.
+pub(crate) fn validate_logon(
+    message: &Message,
+    config: &SessionConfig,
+    logon_sent: bool,
+    next_sender_seq_num: u64,
+) -> Result<(), String> {
+    let sender_comp_id: &str = message
+        .header()
+        .get(fix44::SENDER_COMP_ID)
+        .unwrap_or_default();
+    let target_comp_id: &str = message
+        .header()
+        .get(fix44::TARGET_COMP_ID)
+        .unwrap_or_default();
+    if sender_comp_id != config.target_comp_id || target_comp_id != config.sender_comp_id {
+        return Err(format!(
+            "unexpected CompIDs in logon, SenderCompID(49)={sender_comp_id} TargetCompID(56)={target_comp_id}"
+        ));
+    }
+
+    let heartbeat_interval: u64 = message.get(fix44::HEART_BT_INT).unwrap_or_default();
+    if heartbeat_interval == 0 {
+        return Err("HeartBtInt(108) must be positive".to_string());
+    }
+    if logon_sent && heartbeat_interval != config.heartbeat_interval {
+        return Err(format!(
+            "HeartBtInt(108) doesn't match, expecting {} but received {heartbeat_interval}",
+            config.heartbeat_interval
+        ));
+    }
+
+    let reset_acknowledged = matches!(
+        message.get(fix44::RESET_SEQ_NUM_FLAG),
+        Ok(fix44::ResetSeqNumFlag::Yes)
+    );
+    if logon_sent && config.reset_on_logon && !reset_acknowledged {
+        return Err("sequence reset wasn't acknowledged with ResetSeqNumFlag(141)".to_string());
+    }
+
+    if let Ok(next_expected) = message.get::<u64>(fix44::NEXT_EXPECTED_MSG_SEQ_NUM) {
+        if next_expected > next_sender_seq_num {
+            return Err(format!(
+                "NextExpectedMsgSeqNum(789) too high, expecting at most {next_sender_seq_num} but received {next_expected}"
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+
 pub mod in_memory;
 #[cfg(feature = "redb")]
 pub mod redb;
@@ -10,5 +12,9 @@ pub trait MessageStore {
     async fn next_target_seq_number(&self) -> u64;
     async fn increment_sender_seq_number(&mut self);
     async fn increment_target_seq_number(&mut self);
+    async fn set_next_sender_seq_number(&mut self, seq_number: u64);
+    async fn set_next_target_seq_number(&mut self, seq_number: u64);
+    /// When the store was created or last reset, used to tell which session period it belongs to.
+    async fn creation_time(&self) -> DateTime<Utc>;
     async fn reset(&mut self);
 }
@@ -1,21 +1,41 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
 use crate::store::MessageStore;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct InMemoryMessageStore {
     sender_seq_number: u64,
     target_seq_number: u64,
-    messages: Vec<Vec<u8>>,
+    messages: BTreeMap<u64, Vec<u8>>,
+    creation_time: DateTime<Utc>,
+}
+
+impl Default for InMemoryMessageStore {
+    fn default() -> Self {
+        Self {
+            sender_seq_number: 0,
+            target_seq_number: 0,
+            messages: BTreeMap::new(),
+            creation_time: Utc::now(),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl MessageStore for InMemoryMessageStore {
     async fn add(&mut self, sequence_number: u64, message: &[u8]) {
-        assert_eq!(sequence_number as usize, self.messages.len());
-        self.messages.push(message.to_vec());
+        self.messages.insert(sequence_number, message.to_vec());
     }
 
     async fn get_slice(&self, begin: usize, end: usize) -> Vec<Vec<u8>> {
-        self.messages.as_slice()[begin..=end].to_vec()
+        if begin > end {
+            return vec![];
+        }
+        self.messages
+            .range(begin as u64..=end as u64)
+            .map(|(_, message)| message.clone())
+            .collect()
     }
 
     async fn next_sender_seq_number(&self) -> u64 {
@@ -34,9 +54,22 @@ impl MessageStore for InMemoryMessageStore {
         self.target_seq_number += 1;
     }
 
+    async fn set_next_sender_seq_number(&mut self, seq_number: u64) {
+        self.sender_seq_number = seq_number - 1;
+    }
+
+    async fn set_next_target_seq_number(&mut self, seq_number: u64) {
+        self.target_seq_number = seq_number - 1;
+    }
+
+    async fn creation_time(&self) -> DateTime<Utc> {
+        self.creation_time
+    }
+
     async fn reset(&mut self) {
         self.sender_seq_number = 0;
         self.target_seq_number = 0;
         self.messages.clear();
+        self.creation_time = Utc::now();
     }
 }
@@ -1,3 +1,4 @@
+use chrono::{DateTime, TimeZone, Utc};
 use redb::TableError::TableDoesNotExist;
 use redb::{Database, ReadableTable, TableDefinition};
 use std::path::Path;
@@ -6,6 +7,8 @@ use crate::store::MessageStore;
 
 const MESSAGES_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("messages");
 const SEQ_NUMBER_TABLE: TableDefinition<&str, u64> = TableDefinition::new("seq_numbers");
+/// Holds the creation time of the store in milliseconds since the Unix epoch.
+const METADATA_TABLE: TableDefinition<&str, i64> = TableDefinition::new("metadata");
 
 pub struct RedbMessageStore {
     db: Database,
@@ -15,6 +18,17 @@ impl RedbMessageStore {
     pub fn new(path: impl AsRef<Path>) -> Self {
         let db = Database::create(path).expect("be able to create database");
 
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(METADATA_TABLE).unwrap();
+            if table.get("creation_time").unwrap().is_none() {
+                table
+                    .insert("creation_time", Utc::now().timestamp_millis())
+                    .unwrap();
+            }
+        }
+        write_txn.commit().unwrap();
+
         Self { db }
     }
 }
@@ -31,6 +45,9 @@ impl MessageStore for RedbMessageStore {
     }
 
     async fn get_slice(&self, begin: usize, end: usize) -> Vec<Vec<u8>> {
+        if begin > end {
+            return vec![];
+        }
         let read_txn = self.db.begin_read().unwrap();
         {
             let table = read_txn.open_table(MESSAGES_TABLE).unwrap();
@@ -100,6 +117,33 @@ impl MessageStore for RedbMessageStore {
         write_txn.commit().unwrap();
     }
 
+    async fn set_next_sender_seq_number(&mut self, seq_number: u64) {
+        let write_txn = self.db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(SEQ_NUMBER_TABLE).unwrap();
+            table.insert("sender", seq_number - 1).unwrap();
+        }
+        write_txn.commit().unwrap();
+    }
+
+    async fn set_next_target_seq_number(&mut self, seq_number: u64) {
+        let write_txn = self.db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(SEQ_NUMBER_TABLE).unwrap();
+            table.insert("target", seq_number - 1).unwrap();
+        }
+        write_txn.commit().unwrap();
+    }
+
+    async fn creation_time(&self) -> DateTime<Utc> {
+        let read_txn = self.db.begin_read().unwrap();
+        let table = read_txn.open_table(METADATA_TABLE).unwrap();
+        let millis = table.get("creation_time").unwrap().unwrap().value();
+        Utc.timestamp_millis_opt(millis)
+            .single()
+            .expect("creation time to be valid")
+    }
+
     async fn reset(&mut self) {
         let write_txn = self.db.begin_write().unwrap();
         {
@@ -108,6 +152,10 @@ impl MessageStore for RedbMessageStore {
             seq_no_table.insert("target", 0).unwrap();
             let mut messages_table = write_txn.open_table(MESSAGES_TABLE).unwrap();
             messages_table.drain::<u64>(..).unwrap();
+            let mut metadata_table = write_txn.open_table(METADATA_TABLE).unwrap();
+            metadata_table
+                .insert("creation_time", Utc::now().timestamp_millis())
+                .unwrap();
         }
         write_txn.commit().unwrap();
     }
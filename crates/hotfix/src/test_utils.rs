@@ -0,0 +1,373 @@
+//! Helpers shared by the session tests.
+use hotfix_message::dict::Dictionary;
+use std::collections::VecDeque;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use crate::actors::application::{Application, ApplicationRef};
+use crate::config::{OutboundPolicy, SessionConfig};
+use crate::message::parser::{Parser, RawFixMessage};
+use crate::message::{
+    fix44, Config, FixMessage, Message, MessageRejection, Part, Reject, Timestamp,
+};
+use crate::session::{ConnectionType, SessionRef};
+use crate::store::in_memory::InMemoryMessageStore;
+use crate::transport::FixConnection;
+
+pub const SESSION_COMP_ID: &str = "HOTFIX";
+pub const PEER_COMP_ID: &str = "PEER";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct News {
+    pub headline: String,
+}
+
+impl News {
+    pub fn new(headline: &str) -> Self {
+        Self {
+            headline: headline.to_string(),
+        }
+    }
+}
+
+impl FixMessage for News {
+    fn write(&self, msg: &mut Message) {
+        msg.set(fix44::HEADLINE, self.headline.as_str());
+    }
+
+    fn message_type(&self) -> &str {
+        "B"
+    }
+
+    fn parse(message: &Message) -> Result<Self, MessageRejection> {
+        let headline: &str = message
+            .get(fix44::HEADLINE)
+            .map_err(|_| MessageRejection::missing_field(fix44::HEADLINE))?;
+        Ok(Self::new(headline))
+    }
+
+    fn is_supported(message_type: &str) -> bool {
+        message_type == "B"
+    }
+}
+
+pub struct RecordingApplication {
+    received: mpsc::UnboundedSender<News>,
+    rejects: Option<mpsc::UnboundedSender<(Reject, Option<News>)>>,
+    possible_duplicates: Option<mpsc::UnboundedSender<News>>,
+    sent: Option<mpsc::UnboundedSender<News>>,
+    admin_received: Option<mpsc::UnboundedSender<String>>,
+    refusal: Option<MessageRejection>,
+    replies: Option<Arc<OnceLock<SessionRef<News>>>>,
+}
+
+impl RecordingApplication {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<News>) {
+        let (received, receiver) = mpsc::unbounded_channel();
+        let application = Self {
+            received,
+            rejects: None,
+            possible_duplicates: None,
+            sent: None,
+            admin_received: None,
+            refusal: None,
+            replies: None,
+        };
+        (application, receiver)
+    }
+
+    /// Also records the rejects received by the application.
+    pub fn with_rejects(mut self) -> (Self, mpsc::UnboundedReceiver<(Reject, Option<News>)>) {
+        let (rejects, receiver) = mpsc::unbounded_channel();
+        self.rejects = Some(rejects);
+        (self, receiver)
+    }
+
+    /// Also records the application messages sent to the peer.
+    pub fn with_sent(mut self) -> (Self, mpsc::UnboundedReceiver<News>) {
+        let (sent, receiver) = mpsc::unbounded_channel();
+        self.sent = Some(sent);
+        (self, receiver)
+    }
+
+    /// Marks our logons as test messages and records the types of admin messages received.
+    pub fn with_admin_hooks(mut self) -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (admin_received, receiver) = mpsc::unbounded_channel();
+        self.admin_received = Some(admin_received);
+        (self, receiver)
+    }
+
+    /// Refuses every message received instead of recording it.
+    pub fn with_refusal(mut self, refusal: MessageRejection) -> Self {
+        self.refusal = Some(refusal);
+        self
+    }
+
+    /// Replies to every message received from within the callback, once the session is set.
+    pub fn with_replies(mut self) -> (Self, Arc<OnceLock<SessionRef<News>>>) {
+        let session = Arc::new(OnceLock::new());
+        self.replies = Some(session.clone());
+        (self, session)
+    }
+
+    /// Also records the messages received as possible duplicates.
+    pub fn with_possible_duplicates(mut self) -> (Self, mpsc::UnboundedReceiver<News>) {
+        let (possible_duplicates, receiver) = mpsc::unbounded_channel();
+        self.possible_duplicates = Some(possible_duplicates);
+        (self, receiver)
+    }
+}
+
+#[async_trait::async_trait]
+impl Application<News> for RecordingApplication {
+    async fn on_message_from_app(&self, msg: News) {
+        if let Some(sent) = &self.sent {
+            sent.send(msg).unwrap();
+        }
+    }
+
+    async fn on_message_to_app(
+        &self,
+        msg: News,
+        possible_duplicate: bool,
+    ) -> Result<(), MessageRejection> {
+        if let Some(refusal) = &self.refusal {
+            return Err(refusal.clone());
+        }
+        if possible_duplicate {
+            if let Some(possible_duplicates) = &self.possible_duplicates {
+                possible_duplicates.send(msg.clone()).unwrap();
+            }
+        }
+        let reply = News::new(&format!("re: {}", msg.headline));
+        self.received.send(msg).unwrap();
+        if let Some(session) = self.replies.as_ref().and_then(|replies| replies.get()) {
+            session.post_message(reply).unwrap();
+        }
+        Ok(())
+    }
+
+    async fn on_reject(&self, reject: Reject, rejected_message: Option<News>) {
+        if let Some(rejects) = &self.rejects {
+            rejects.send((reject, rejected_message)).unwrap();
+        }
+    }
+
+    async fn on_logout(&mut self, _reason: &str) {}
+
+    async fn on_admin_message_to_peer(&self, msg: &mut Message) {
+        if self.admin_received.is_some() && message_type(msg) == "A" {
+            msg.set(fix44::TEST_MESSAGE_INDICATOR, true);
+        }
+    }
+
+    async fn on_admin_message_to_app(&self, msg: &Message) {
+        if let Some(admin_received) = &self.admin_received {
+            admin_received.send(message_type(msg).to_string()).unwrap();
+        }
+    }
+}
+
+pub fn session_config(sender_comp_id: &str, target_comp_id: &str, port: u16) -> SessionConfig {
+    SessionConfig {
+        begin_string: "FIX.4.4".to_string(),
+        sender_comp_id: sender_comp_id.to_string(),
+        target_comp_id: target_comp_id.to_string(),
+        data_dictionary_path: None,
+        transport_data_dictionary_path: None,
+        connection_host: "127.0.0.1".to_string(),
+        connection_port: port,
+        tls_config: None,
+        heartbeat_interval: 30,
+        reconnect_interval: 1,
+        logout_timeout: 10,
+        max_latency: 120,
+        resend_chunk_size: 1000,
+        resend_request_chunk_size: 0,
+        reset_on_logon: true,
+        outbound_policy: OutboundPolicy::Queue,
+        schedule: None,
+        username: None,
+        password: None,
+        new_password: None,
+        default_appl_ver_id: None,
+        validation: None,
+    }
+}
+
+/// The other side of a session under test, sending and receiving raw FIX messages.
+pub struct Counterparty {
+    stream: DuplexStream,
+    parser: Parser,
+    pending: VecDeque<RawFixMessage>,
+    begin_string: String,
+    dictionary: Dictionary,
+    /// Only set when the counterparty speaks FIXT.1.1.
+    application_dictionary: Option<Dictionary>,
+    pub next_seq_num: u64,
+}
+
+impl Counterparty {
+    /// Connects a new counterparty to the session over an in-memory stream.
+    pub async fn connect(session: SessionRef<News>) -> Self {
+        let (session_stream, stream) = duplex(64 * 1024);
+        FixConnection::accept(session_stream, session, Parser::default(), vec![])
+            .await
+            .unwrap();
+
+        Self {
+            stream,
+            parser: Parser::default(),
+            pending: VecDeque::new(),
+            begin_string: "FIX.4.4".to_string(),
+            dictionary: Dictionary::fix44(),
+            application_dictionary: None,
+            next_seq_num: 1,
+        }
+    }
+
+    /// Speaks another version than FIX 4.4, FIXT.1.1 sessions use FIX 5.0 SP2 application messages.
+    pub fn with_begin_string(mut self, begin_string: &str) -> Self {
+        self.begin_string = begin_string.to_string();
+        match begin_string {
+            "FIX.4.2" => self.dictionary = Dictionary::fix42(),
+            "FIXT.1.1" => {
+                self.dictionary = Dictionary::fixt11();
+                self.application_dictionary = Some(Dictionary::fix50sp2());
+            }
+            _ => {}
+        }
+        self
+    }
+
+    pub async fn send(&mut self, message_type: &str, build: impl FnOnce(&mut Message)) {
+        let seq_num = self.next_seq_num;
+        self.next_seq_num += 1;
+        self.send_with_seq_num(message_type, seq_num, build).await;
+    }
+
+    pub async fn send_with_seq_num(
+        &mut self,
+        message_type: &str,
+        seq_num: u64,
+        build: impl FnOnce(&mut Message),
+    ) {
+        let mut msg = Message::new(&self.begin_string, message_type);
+        msg.set(fix44::SENDER_COMP_ID, PEER_COMP_ID);
+        msg.set(fix44::TARGET_COMP_ID, SESSION_COMP_ID);
+        msg.set(fix44::MSG_SEQ_NUM, seq_num);
+        msg.set(fix44::SENDING_TIME, Timestamp::utc_now());
+        build(&mut msg);
+
+        let data = msg.encode(&Config::default());
+        self.stream.write_all(&data).await.unwrap();
+    }
+
+    /// Waits for the next message sent by the session.
+    pub async fn receive(&mut self) -> Message {
+        self.try_receive(Duration::from_secs(5))
+            .await
+            .expect("session to send a message")
+    }
+
+    pub async fn try_receive(&mut self, wait: Duration) -> Option<Message> {
+        loop {
+            if let Some(raw) = self.pending.pop_front() {
+                let application_dictionary = self
+                    .application_dictionary
+                    .as_ref()
+                    .unwrap_or(&self.dictionary);
+                return Some(
+                    Message::from_bytes_with_dictionaries(
+                        &Config::default(),
+                        &self.dictionary,
+                        application_dictionary,
+                        raw.as_bytes(),
+                    )
+                    .expect("the counterparty to receive valid messages"),
+                );
+            }
+
+            let mut buf = vec![];
+            match timeout(wait, self.stream.read_buf(&mut buf)).await {
+                Ok(Ok(n)) if n > 0 => self.pending.extend(self.parser.parse(&buf)),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Reads until the session closes the connection, returning the types of the messages
+    /// received meanwhile, or `None` if it's still open after `wait`.
+    pub async fn wait_for_disconnect(&mut self, wait: Duration) -> Option<Vec<String>> {
+        timeout(wait, async {
+            loop {
+                let mut buf = vec![];
+                match self.stream.read_buf(&mut buf).await {
+                    Ok(n) if n > 0 => self.pending.extend(self.parser.parse(&buf)),
+                    _ => break,
+                }
+            }
+        })
+        .await
+        .ok()?;
+        let message_types = self
+            .pending
+            .drain(..)
+            .map(|raw| {
+                let message_type = raw.get_raw(fix44::MSG_TYPE.tag).unwrap_or_default();
+                String::from_utf8_lossy(message_type).to_string()
+            })
+            .collect();
+        Some(message_types)
+    }
+
+    /// Completes the logon handshake initiated by the session.
+    pub async fn logon(&mut self) {
+        let logon = self.receive().await;
+        assert_eq!(message_type(&logon), "A");
+        let heartbeat_interval: u64 = logon.get(fix44::HEART_BT_INT).unwrap();
+        self.send("A", |msg| {
+            msg.set(fix44::ENCRYPT_METHOD, fix44::EncryptMethod::None);
+            msg.set(fix44::HEART_BT_INT, heartbeat_interval);
+            msg.set(fix44::RESET_SEQ_NUM_FLAG, fix44::ResetSeqNumFlag::Yes);
+        })
+        .await;
+    }
+
+    /// Waits until the session has processed everything sent so far, using a test request.
+    pub async fn sync(&mut self) {
+        self.send("1", |msg| msg.set(fix44::TEST_REQ_ID, "sync"))
+            .await;
+        let heartbeat = self.receive().await;
+        assert_eq!(message_type(&heartbeat), "0");
+    }
+}
+
+/// Creates an initiator session connected to a counterparty that is already logged on.
+pub async fn logged_on_session(
+    config: SessionConfig,
+) -> (
+    SessionRef<News>,
+    Counterparty,
+    mpsc::UnboundedReceiver<News>,
+) {
+    let (application, received) = RecordingApplication::new();
+    let session = SessionRef::new(
+        config,
+        ConnectionType::Initiator,
+        ApplicationRef::new(application),
+        InMemoryMessageStore::default(),
+    )
+    .unwrap();
+    let mut counterparty = Counterparty::connect(session.clone()).await;
+    counterparty.logon().await;
+
+    (session, counterparty, received)
+}
+
+pub fn message_type(message: &Message) -> &str {
+    message.header().get(fix44::MSG_TYPE).unwrap()
+}
@@ -1,11 +1,13 @@
 use std::io;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::oneshot;
 
 use crate::actors::socket_reader::ReaderRef;
 use crate::actors::socket_writer::WriterRef;
 use crate::config::SessionConfig;
+use crate::message::parser::{Parser, RawFixMessage};
 use crate::message::FixMessage;
-use crate::session::SessionRef;
+use crate::session::{SessionError, SessionRef};
 use crate::transport::tcp::create_tcp_connection;
 use crate::transport::tls::create_tcp_over_tls_connection;
 
@@ -32,6 +34,45 @@ impl FixConnection {
         Ok(conn)
     }
 
+    /// Hands an inbound connection over to the session it has been routed to.
+    ///
+    /// The acceptor has to read the first logon message to find the right session,
+    /// so the messages it has already parsed are forwarded to the session before
+    /// the reader takes over, together with any bytes still buffered in the parser.
+    pub async fn accept<M, Stream>(
+        stream: Stream,
+        session_ref: SessionRef<M>,
+        parser: Parser,
+        received: Vec<RawFixMessage>,
+    ) -> Result<Self, SessionError>
+    where
+        M: FixMessage,
+        Stream: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        let (reader_stop, stop_signal) = oneshot::channel();
+
+        let writer_ref = WriterRef::new(writer, reader_stop);
+        // the session refuses the connection if it's already connected, in which case
+        // nothing received on it may reach the session
+        session_ref.register_writer(writer_ref.clone()).await?;
+        for msg in received {
+            session_ref.new_fix_message_received(msg).await?;
+        }
+        let reader_ref = ReaderRef::with_parser(
+            reader,
+            writer_ref.connection_id(),
+            session_ref,
+            parser,
+            stop_signal,
+        );
+
+        Ok(FixConnection {
+            _writer: writer_ref,
+            _reader: reader_ref,
+        })
+    }
+
     pub fn get_writer(&self) -> WriterRef {
         self._writer.clone()
     }
@@ -47,9 +88,10 @@ where
     Stream: AsyncRead + AsyncWrite + Send + 'static,
 {
     let (reader, writer) = tokio::io::split(stream);
+    let (reader_stop, stop_signal) = oneshot::channel();
 
-    let writer_ref = WriterRef::new(writer);
-    let reader_ref = ReaderRef::new(reader, session_ref);
+    let writer_ref = WriterRef::new(writer, reader_stop);
+    let reader_ref = ReaderRef::new(reader, writer_ref.connection_id(), session_ref, stop_signal);
 
     FixConnection {
         _writer: writer_ref,
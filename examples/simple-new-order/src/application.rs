@@ -1,5 +1,6 @@
+use hotfix::message::{MessageRejection, Reject};
 use hotfix::Application;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::messages::Message;
 
@@ -8,24 +9,29 @@ pub struct TestApplication {}
 
 #[async_trait::async_trait]
 impl Application<Message> for TestApplication {
-    async fn on_message_from_app(&self, _msg: Message) {
-        todo!()
+    async fn on_message_from_app(&self, msg: Message) {
+        info!("sending message: {:?}", msg);
     }
 
-    async fn on_message_to_app(&self, msg: Message) {
-        match msg {
-            Message::NewOrderSingle(_) => {
-                unimplemented!("we should not receive orders");
-            }
-            Message::UnimplementedMessage(data) => {
-                let pretty_bytes: Vec<u8> = data
-                    .iter()
-                    .map(|b| if *b == b'\x01' { b'|' } else { *b })
-                    .collect();
-                let s = std::str::from_utf8(&pretty_bytes).unwrap_or("invalid characters");
-                info!("received message: {:?}", s);
-            }
+    async fn on_message_to_app(
+        &self,
+        msg: Message,
+        _possible_duplicate: bool,
+    ) -> Result<(), MessageRejection> {
+        if let Message::ExecutionReport(report) = msg {
+            info!(
+                "order {} is {:?} after {:?}",
+                report.cl_ord_id, report.ord_status, report.exec_type
+            );
         }
+        Ok(())
+    }
+
+    async fn on_reject(&self, reject: Reject, _rejected_message: Option<Message>) {
+        warn!(
+            "message {} was rejected: {:?}",
+            reject.ref_seq_num, reject.text
+        );
     }
 
     async fn on_logout(&mut self, _reason: &str) {
@@ -66,6 +66,9 @@ async fn user_loop(session: Initiator<Message>) {
 
         match command_task.await.unwrap().trim() {
             "q" => {
+                if let Err(err) = session.logout("user requested logout").await {
+                    println!("Failed to log out: {err}");
+                }
                 return;
             }
             "s" => {
@@ -84,7 +87,7 @@ async fn send_message(session: &Initiator<Message>) {
     let order = NewOrderSingle {
         transact_time: Timestamp::utc_now(),
         symbol: "EUR/USD".to_string(),
-        cl_ord_id: order_id,
+        cl_ord_id: order_id.clone(),
         side: fix44::Side::Buy,
         order_qty: 230,
         settlement_date: Date::new(2023, 9, 19).unwrap(),
@@ -95,7 +98,10 @@ async fn send_message(session: &Initiator<Message>) {
     };
     let msg = Message::NewOrderSingle(order);
 
-    session.send_message(msg).await;
+    match session.send_message(msg).await {
+        Ok(delivery) => println!("Order {order_id}: {:?}", delivery.outcome),
+        Err(err) => println!("Failed to send order {order_id}: {err}"),
+    }
 }
 
 async fn start_session(config_path: &str, app: TestApplication) -> Initiator<Message> {
@@ -103,5 +109,7 @@ async fn start_session(config_path: &str, app: TestApplication) -> Initiator<Mes
     let session_config = config.sessions.pop().expect("config to include a session");
     let store = hotfix::store::redb::RedbMessageStore::new("session.db");
 
-    Initiator::new(session_config, app, store).await
+    Initiator::new(session_config, app, store)
+        .await
+        .expect("session dictionaries to load")
 }
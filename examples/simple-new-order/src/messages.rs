@@ -1,5 +1,5 @@
 use hotfix::field_types::{Date, Timestamp};
-use hotfix::message::{fix44, FixMessage, Part, RepeatingGroup};
+use hotfix::message::{fix44, FixMessage, MessageRejection, Part, RepeatingGroup};
 use hotfix::Message as HotfixMessage;
 
 #[derive(Debug, Clone)]
@@ -19,10 +19,20 @@ pub struct NewOrderSingle {
     pub allocation_quantity: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    pub order_id: String,
+    pub cl_ord_id: String,
+    pub exec_id: String,
+    pub exec_type: fix44::ExecType,
+    pub ord_status: fix44::OrdStatus,
+    pub symbol: String,
+}
+
 #[derive(Debug, Clone)]
 pub enum Message {
     NewOrderSingle(NewOrderSingle),
-    UnimplementedMessage(Vec<u8>),
+    ExecutionReport(ExecutionReport),
 }
 
 impl FixMessage for Message {
@@ -45,19 +55,48 @@ impl FixMessage for Message {
                 allocation.set(fix44::ALLOC_QTY, order.allocation_quantity);
                 msg.set_groups(vec![allocation]);
             }
-            _ => unimplemented!(),
+            Self::ExecutionReport(report) => {
+                msg.set(fix44::ORDER_ID, report.order_id.as_str());
+                msg.set(fix44::CL_ORD_ID, report.cl_ord_id.as_str());
+                msg.set(fix44::EXEC_ID, report.exec_id.as_str());
+                msg.set(fix44::EXEC_TYPE, report.exec_type);
+                msg.set(fix44::ORD_STATUS, report.ord_status);
+                msg.set(fix44::SYMBOL, report.symbol.as_str());
+            }
         }
     }
 
     fn message_type(&self) -> &str {
         match self {
             Self::NewOrderSingle(_) => "D",
-            _ => unimplemented!(),
+            Self::ExecutionReport(_) => "8",
         }
     }
 
-    fn parse(message: &HotfixMessage) -> Self {
-        let message_type: &str = message.header().get(fix44::MSG_TYPE).unwrap();
-        Self::UnimplementedMessage(message_type.as_bytes().to_vec())
+    fn parse(message: &HotfixMessage) -> Result<Self, MessageRejection> {
+        let field = |field| {
+            message
+                .get::<&str>(field)
+                .map(str::to_string)
+                .map_err(|_| MessageRejection::missing_field(field))
+        };
+        let report = ExecutionReport {
+            order_id: field(fix44::ORDER_ID)?,
+            cl_ord_id: field(fix44::CL_ORD_ID)?,
+            exec_id: field(fix44::EXEC_ID)?,
+            exec_type: message
+                .get(fix44::EXEC_TYPE)
+                .map_err(|_| MessageRejection::missing_field(fix44::EXEC_TYPE))?,
+            ord_status: message
+                .get(fix44::ORD_STATUS)
+                .map_err(|_| MessageRejection::missing_field(fix44::ORD_STATUS))?,
+            symbol: field(fix44::SYMBOL)?,
+        };
+        Ok(Self::ExecutionReport(report))
+    }
+
+    /// We only send orders, execution reports are the only messages we receive.
+    fn is_supported(message_type: &str) -> bool {
+        message_type == "8"
     }
 }